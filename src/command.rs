@@ -1,20 +1,88 @@
 use crate::{
-    config::{Config, ConstructedGameInfo, GameName},
+    config::{Config, ConstructedGameInfo, GameName, LoadingInput, RepeatLimits, DEFAULT_FPS},
     database,
-    game_runner::{self, GameRunner, SfxRequest},
+    emulator::EmulatorAction,
+    game_log,
+    game_runner::{self, GameEvent, GameRunner, SfxRequest},
+    game_search::{self, GameMatch},
+    meter::{self, MeterVote, ModeMeter},
+    schedule::{self, ScheduleSlot, SlotTime},
+    vote::{self, Poll, PollResult, TieBreak},
 };
 use anyhow::{anyhow, Context};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use rusqlite::Connection;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tokio::sync::{
-    mpsc::{Receiver, Sender, UnboundedSender},
+    mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender},
     oneshot,
 };
 use tracing::info;
 
 const CONFIG_KV_ANARCHY_MODE: &str = "anarchy_mode";
 const CONFIG_KV_COOLDOWN_DURATION: &str = "cooldown";
+const CONFIG_KV_VOTE_WINDOW: &str = "vote_window";
+const CONFIG_KV_VOTE_MIN_VOTES: &str = "vote_min_votes";
+const CONFIG_KV_VOTE_TIE_BREAK: &str = "vote_tie_break";
+const CONFIG_KV_METER_VALUE: &str = "meter_value";
+const CONFIG_KV_METER_LOCKED: &str = "meter_locked";
+const CONFIG_KV_CURRENT_GAME: &str = "current_game";
+const CONFIG_KV_GAME_POLL_WINDOW: &str = "game_poll_window";
+const CONFIG_KV_GAME_POLL_MIN_VOTES: &str = "game_poll_min_votes";
+const CONFIG_KV_GAME_POLL_OPTIONS: &str = "game_poll_options";
+const CONFIG_KV_SCHEDULE_SLOT: &str = "schedule_slot";
+const CONFIG_KV_SCHEDULE_STOP_PENDING: &str = "schedule_stop_pending";
+
+const DEFAULT_VOTE_WINDOW_MS: i64 = 5000;
+const DEFAULT_GAME_POLL_WINDOW_MS: i64 = 60000;
+const DEFAULT_GAME_POLL_MIN_VOTES: u64 = 3;
+/// How long viewers wait after opening a game poll before they can open another
+const GAME_POLL_OPEN_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(600);
+const DEFAULT_GAME_POLL_OPTIONS: usize = 5;
+const MAX_GAME_POLL_OPTIONS: usize = 10;
+
+/// Auto-saves are kept in a named save slot so they can be loaded like any other slot
+pub const AUTOSAVE_SLOT_NAME: &str = "autosave";
+
+/// Time given to the emulator to finish writing an auto-save before the game is stopped
+const AUTOSAVE_BEFORE_STOP_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The schedule is checked at least this often, in case the clock changes
+const MAX_SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Upcoming slots shown by `tp schedule`
+const SCHEDULE_UPCOMING_SLOTS: usize = 3;
+
+/// Separates the steps of a chain of movements
+const CHAIN_SEPARATORS: [char; 2] = ['>', ','];
+
+/// Chains with more steps than this aren't parsed as movements
+const MAX_CHAIN_STEPS: usize = 10;
+
+/// Most presses that can be asked for with `a*5`, games can cap this lower
+const MAX_REPEATS: u64 = 100;
+
+/// Longest a movement can be held for, in milliseconds
+const MAX_DURATION_MS: u64 = 5000;
+/// Longest a movement can be held for in frames, still capped to `MAX_DURATION_MS` once resolved
+const MAX_FRAMES: u64 = 300;
+/// Longest a chained or repeated movement takes to play, in milliseconds
+const MAX_PLAY_TIME_MS: u64 = 10000;
+
+/// Movements held while a game loads beyond this are discarded
+const MAX_HELD_MOVEMENTS: usize = 20;
+
+/// Ambiguous game names list at most this many possible matches
+const MAX_GAME_SUGGESTIONS: usize = 5;
+
+const DEFAULT_GAME_LOG_LINES: usize = 5;
+const MAX_GAME_LOG_LINES: usize = 10;
+
+/// Long replies are cut down to this many characters to fit in a chat message
+const MAX_REPLY_LEN: usize = 450;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AnarchyType {
@@ -22,6 +90,7 @@ pub enum AnarchyType {
     Democracy,
     Restricted,
     Streaming,
+    Voting,
 }
 
 impl AnarchyType {
@@ -31,6 +100,7 @@ impl AnarchyType {
             Self::Democracy => "democracy",
             Self::Restricted => "restricted",
             Self::Streaming => "streaming",
+            Self::Voting => "voting",
         }
     }
 
@@ -40,12 +110,14 @@ impl AnarchyType {
             "democracy" => Some(Self::Democracy),
             "restricted" => Some(Self::Restricted),
             "streaming" => Some(Self::Streaming),
+            "voting" => Some(Self::Voting),
             _ => None,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Privilege {
     Standard = 0,
     Operator = 1,
@@ -96,15 +168,95 @@ pub enum Movement {
     Mode,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, EnumIter)]
+#[non_exhaustive]
+pub enum Axis {
+    X,
+    Y,
+    RX,
+    RY,
+    Z,
+    RZ,
+}
+
+pub const AXIS_MIN: i32 = 0;
+pub const AXIS_MAX: i32 = 255;
+pub const AXIS_CENTER: i32 = 128;
+
+impl Axis {
+    pub const fn is_stick(self) -> bool {
+        matches!(self, Self::X | Self::Y | Self::RX | Self::RY)
+    }
+
+    /// Sticks rest in the center, triggers rest fully released
+    pub const fn rest_value(self) -> i32 {
+        if self.is_stick() {
+            AXIS_CENTER
+        } else {
+            AXIS_MIN
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    /// Pressed this many times
+    Times(u64),
+    /// Pressed over and over for this many milliseconds
+    For(u64),
+    /// Pressed over and over at the turbo rate for this many milliseconds
+    Turbo(u64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hold {
+    /// Keeps the movements pressed until they're released or `duration` passes
+    Press,
+    /// Releases held movements
+    Release,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MovementPacket {
     pub movements: Vec<Movement>,
+    pub axes: Vec<(Axis, i32)>,
     pub duration: u64,
     pub stagger: u64,
     pub blocking: bool,
+    /// Packets played in order after this one is released, without other packets in between
+    pub chain: Vec<MovementPacket>,
+    /// Presses the inputs again after each release, held and released for `duration` each time
+    pub repeat: Option<Repeat>,
+    /// Holds or releases the movements as soon as the packet is received
+    pub hold: Option<Hold>,
+    /// Held for this many frames, converted into `duration` with the current game's `fps`
+    pub frames: Option<u64>,
 }
 
 impl MovementPacket {
+    /// Holds or releases buttons, held buttons are released after `timeout`
+    pub fn hold(movements: Vec<Movement>, hold: Hold, timeout: std::time::Duration) -> Self {
+        MovementPacket {
+            movements,
+            axes: vec![],
+            duration: timeout.as_millis() as u64,
+            stagger: 0,
+            blocking: false,
+            chain: vec![],
+            repeat: None,
+            hold: Some(hold),
+            frames: None,
+        }
+    }
+
+    pub fn release_all() -> Self {
+        Self::hold(
+            Movement::iter().collect(),
+            Hold::Release,
+            Default::default(),
+        )
+    }
+
     pub fn contains_direction(&self) -> bool {
         self.movements.iter().any(|movement| {
             matches!(
@@ -116,7 +268,50 @@ impl MovementPacket {
                     | Movement::Start
                     | Movement::Select
             )
-        })
+        }) || self.axes.iter().any(|(axis, _)| axis.is_stick())
+    }
+}
+
+impl std::fmt::Display for MovementPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut inputs: Vec<String> = self
+            .movements
+            .iter()
+            .map(|movement| format!("{:?}", movement).to_lowercase())
+            .collect();
+        inputs.extend(
+            self.axes
+                .iter()
+                .map(|(axis, value)| format!("{:?}={}", axis, value).to_lowercase()),
+        );
+
+        match self.repeat {
+            Some(Repeat::Times(presses)) => write!(f, "{}*{}", inputs.join(" "), presses)?,
+            Some(Repeat::For(duration)) => write!(
+                f,
+                "mash {} {}s",
+                inputs.join(" "),
+                duration as f64 / 1000f64
+            )?,
+            Some(Repeat::Turbo(duration)) => write!(
+                f,
+                "turbo {} {}s",
+                inputs.join(" "),
+                duration as f64 / 1000f64
+            )?,
+            None => write!(
+                f,
+                "{} {}s",
+                inputs.join(" "),
+                self.duration as f64 / 1000f64
+            )?,
+        }
+
+        for packet in self.chain.iter() {
+            write!(f, " > {}", packet)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -128,35 +323,94 @@ pub enum PartialCommand {
     Block,
     Unblock,
     Game,
+    GameSearch,
     List,
     SetCooldown,
     SetAnarchyMode,
     PlaySfx,
+    VoteSettings,
+    MeterVote,
+    Meter,
+    GameLog,
+    Nominate,
+    VoteGame,
+    GamePoll,
+    Schedule,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Command {
     Movement(MovementPacket),
+    /// A movement using alias words, resolved against the current game's aliases
+    Alias(String),
+    /// Keeps buttons pressed until they're released or time out
+    Hold(Vec<Movement>),
+    Release(Vec<Movement>),
+    ReleaseAll,
     AddOperator(String),
     RemoveOperator(String),
     Block(String, Option<chrono::DateTime<chrono::Utc>>),
     Unblock(String),
     Game(GameName),
+    /// Switches to a random game, with the tag if given
+    RandomGame(Option<String>),
+    SearchGames(String),
     Stop,
     Partial(PartialCommand),
     ListBlocked,
     ListOperators,
-    ListGames,
+    ListGames(Option<String>),
     PrintHelp,
-    SaveState,
-    LoadState,
-    Reset,
+    Emulator(EmulatorAction),
+    SaveSlot(SaveSlot),
+    LoadSlot(SaveSlot),
+    ListSlots,
+    PrintAutosave,
+    PrintGameStatus,
+    GameLog(usize),
     SetCooldown(chrono::Duration),
     SetAnarchyMode(AnarchyType),
     PrintAnarchyMode,
     PlaySfx(String),
     Controls(Option<String>),
+    SetVoteWindow(chrono::Duration),
+    SetVoteMinVotes(u64),
+    SetVoteTieBreak(TieBreak),
+    PrintVoteSettings,
+    MeterVote(MeterVote),
+    LockMeter(bool),
+    /// Adds a game to the open game poll, opening one if needed
+    Nominate(String),
+    /// Votes for a game poll option, numbered from 1
+    VoteGame(usize),
+    /// Opens a game poll with random games, with the tag if given
+    StartGamePoll(Option<String>),
+    CancelGamePoll,
+    SetGamePollWindow(chrono::Duration),
+    SetGamePollMinVotes(u64),
+    SetGamePollOptions(usize),
+    PrintGamePoll,
+    PrintSchedule,
+    /// Ends the current scheduled slot early, stopping its game
+    SkipScheduledSlot,
+    /// Keeps the current scheduled slot from stopping its game when it ends
+    OverrideScheduledSlot,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveSlot {
+    Number(u64),
+    Name(String),
+}
+
+impl SaveSlot {
+    fn parse(token: &str) -> Self {
+        match token.parse() {
+            Ok(slot) => SaveSlot::Number(slot),
+            Err(_) => SaveSlot::Name(token.to_owned()),
+        }
+    }
 }
 
 pub fn parse_movement_token(token: &str) -> Option<Movement> {
@@ -182,42 +436,303 @@ pub fn parse_movement_token(token: &str) -> Option<Movement> {
     Some(movement)
 }
 
-fn parse_movement(tokens: &Vec<&str>) -> Option<Command> {
+pub fn parse_stick_token(token: &str) -> Option<(Axis, Axis)> {
+    match token {
+        "ls" => Some((Axis::X, Axis::Y)),
+        "rs" => Some((Axis::RX, Axis::RY)),
+        _ => None,
+    }
+}
+
+pub fn parse_trigger_token(token: &str) -> Option<Axis> {
+    match token {
+        "lt" | "tl" => Some(Axis::Z),
+        "rt" | "tr" => Some(Axis::RZ),
+        _ => None,
+    }
+}
+
+/// Parses a stick direction into an angle in degrees, 0 being right and 90 being up
+fn parse_stick_direction(token: &str) -> Option<f64> {
+    let angle = match token {
+        "right" | "r" | "e" => 0f64,
+        "ne" => 45f64,
+        "up" | "u" | "n" => 90f64,
+        "nw" => 135f64,
+        "left" | "l" | "w" => 180f64,
+        "sw" => 225f64,
+        "down" | "d" | "s" => 270f64,
+        "se" => 315f64,
+        _ => str::parse::<f64>(token)
+            .ok()
+            .filter(|angle| angle.is_finite())?,
+    };
+
+    Some(angle)
+}
+
+fn parse_percentage(token: &str) -> Option<f64> {
+    token
+        .strip_suffix('%')
+        .and_then(|pct| str::parse::<f64>(pct).ok())
+        .filter(|pct| *pct >= 0f64)
+        .filter(|pct| *pct <= 100f64)
+        .map(|pct| pct / 100f64)
+}
+
+fn stick_position(axes: (Axis, Axis), angle: f64, magnitude: f64) -> [(Axis, i32); 2] {
+    let (x_axis, y_axis) = axes;
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // The center isn't midway between the limits, so each side has its own range
+    let deflect = |offset: f64| {
+        let range = match offset < 0.0 {
+            true => AXIS_CENTER - AXIS_MIN,
+            false => AXIS_MAX - AXIS_CENTER,
+        };
+        AXIS_CENTER + (offset * magnitude * range as f64).round() as i32
+    };
+
+    // The Y axis grows downwards, so up is towards the minimum
+    let x = deflect(cos);
+    let y = deflect(-sin);
+
+    [
+        (x_axis, x.clamp(AXIS_MIN, AXIS_MAX)),
+        (y_axis, y.clamp(AXIS_MIN, AXIS_MAX)),
+    ]
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PacketDuration {
+    Millis(u64),
+    Frames(u64),
+}
+
+/// Parses a duration in seconds like `0.5`, or with a unit like `250ms`, `2s` or `3f`
+fn parse_duration(token: &str) -> Option<PacketDuration> {
+    if let Some(frames) = token.strip_suffix('f') {
+        return str::parse::<u64>(frames)
+            .ok()
+            .filter(|frames| *frames <= MAX_FRAMES)
+            .map(PacketDuration::Frames);
+    }
+
+    let millis = match token.strip_suffix("ms") {
+        Some(millis) => str::parse::<u64>(millis).ok()?,
+        None => str::parse::<f64>(token.strip_suffix('s').unwrap_or(token))
+            .ok()
+            .filter(|sec| *sec >= 0f64)
+            .filter(|sec| *sec * 1000f64 <= MAX_DURATION_MS as f64)
+            .map(|sec| (sec * 1000f64) as u64)?,
+    };
+
+    Some(millis)
+        .filter(|millis| *millis <= MAX_DURATION_MS)
+        .map(PacketDuration::Millis)
+}
+
+fn parse_packet(tokens: &Vec<&str>) -> Option<MovementPacket> {
     if tokens.is_empty() {
         return None;
     }
 
     let mut movements = Vec::new();
-    let mut duration = Some(100);
-    for (idx, token) in tokens.iter().enumerate() {
-        if let Some(movement) = parse_movement_token(token) {
+    let mut axes: Vec<(Axis, i32)> = Vec::new();
+    let mut duration = Some(PacketDuration::Millis(100));
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = tokens[idx];
+
+        // Triggers followed by a percentage are pressed as analog inputs
+        let trigger = parse_trigger_token(token).and_then(|axis| {
+            let pressure = tokens.get(idx + 1).and_then(|t| parse_percentage(t))?;
+            Some((axis, pressure))
+        });
+
+        if let Some((axis, pressure)) = trigger {
+            if axes.iter().any(|(a, _)| *a == axis) {
+                return None;
+            }
+
+            idx += 1;
+            let value = AXIS_MIN + ((AXIS_MAX - AXIS_MIN) as f64 * pressure).round() as i32;
+            axes.push((axis, value));
+        } else if let Some(movement) = parse_movement_token(token) {
             movements.push(movement);
+        } else if let Some(stick) = parse_stick_token(token) {
+            // A stick must be followed by a direction, and optionally a magnitude
+            idx += 1;
+            let angle = parse_stick_direction(tokens.get(idx)?)?;
+            let magnitude = match tokens.get(idx + 1).and_then(|t| parse_percentage(t)) {
+                Some(magnitude) => {
+                    idx += 1;
+                    magnitude
+                }
+                None => 1f64,
+            };
+
+            for (axis, value) in stick_position(stick, angle, magnitude) {
+                if axes.iter().any(|(a, _)| *a == axis) {
+                    return None;
+                }
+
+                axes.push((axis, value));
+            }
         } else if idx == tokens.len() - 1 {
-            duration = str::parse::<f64>(token)
-                .ok()
-                .filter(|sec| *sec <= 5f64)
-                .filter(|sec| *sec >= 0f64)
-                .map(|sec| sec * 1000f64)
-                .map(|sec| sec as u64);
+            duration = parse_duration(token);
         } else {
             return None;
         }
+
+        idx += 1;
     }
 
-    if movements.is_empty() {
+    if movements.is_empty() && axes.is_empty() {
         return None;
     }
 
-    duration.map(|duration| {
-        Command::Movement(MovementPacket {
-            movements,
-            duration,
-            stagger: 0,
-            blocking: false,
-        })
+    let (duration, frames) = match duration? {
+        PacketDuration::Millis(millis) => (millis, None),
+        // Played at the default frame rate until resolved against the current game
+        PacketDuration::Frames(frames) => (frames_to_millis(frames, DEFAULT_FPS), Some(frames)),
+    };
+
+    Some(MovementPacket {
+        movements,
+        axes,
+        duration,
+        stagger: 0,
+        blocking: false,
+        chain: vec![],
+        repeat: None,
+        hold: None,
+        frames,
+    })
+}
+
+/// Parses a packet, which can be repeated with `a*5` (or `a *5`, `a * 5`), `mash a 3` or `turbo a 3`
+fn parse_step(tokens: &Vec<&str>) -> Option<MovementPacket> {
+    match &tokens[..] {
+        [first, ..] if tokens.iter().any(|token| token.contains('*')) => {
+            // Spaces are allowed around the `*`
+            let joined = tokens.concat();
+            let (input, presses) = joined.split_once('*')?;
+            // Only a single input can be repeated, so `a b*5` isn't read as `ab*5`
+            if first.split('*').next() != Some(input) {
+                return None;
+            }
+            let presses = str::parse::<u64>(presses)
+                .ok()
+                .filter(|presses| (1..=MAX_REPEATS).contains(presses))?;
+            let mut packet = parse_packet(&vec![input])?;
+            packet.repeat = Some(Repeat::Times(presses));
+            Some(packet)
+        }
+        ["mash", .., duration] if parse_duration(duration).is_some() => {
+            let mut packet = parse_packet(&tokens[1..].to_vec())?;
+            packet.repeat = Some(Repeat::For(packet.duration));
+            Some(packet)
+        }
+        ["turbo", .., duration] if parse_duration(duration).is_some() => {
+            let mut packet = parse_packet(&tokens[1..].to_vec())?;
+            packet.repeat = Some(Repeat::Turbo(packet.duration));
+            Some(packet)
+        }
+        _ => parse_packet(tokens),
+    }
+}
+
+/// Parses a packet, or a chain of packets separated by `>` or `,` like `down>right>a` or `down, right, a 0.2`
+fn parse_movement(tokens: &Vec<&str>) -> Option<Command> {
+    let input = tokens.join(" ");
+    if !input.contains(CHAIN_SEPARATORS) {
+        return parse_step(tokens).map(Command::Movement);
+    }
+
+    let mut steps = input
+        .split(CHAIN_SEPARATORS)
+        .map(|step| parse_step(&step.split_whitespace().collect()))
+        .collect::<Option<Vec<MovementPacket>>>()?;
+    if steps.len() > MAX_CHAIN_STEPS {
+        return None;
+    }
+
+    let mut packet = steps.remove(0);
+    packet.chain = steps;
+    Some(Command::Movement(packet))
+}
+
+/// Parses the buttons of `hold` and `release`, every token must be one
+fn parse_buttons(tokens: &[&str]) -> Option<Vec<Movement>> {
+    let mut buttons = Vec::new();
+    for token in tokens.iter() {
+        let button = parse_movement_token(token)?;
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    match buttons.is_empty() {
+        true => None,
+        false => Some(buttons),
+    }
+}
+
+/// Splits input into words, with chain separators as words of their own
+fn alias_tokens(input: &str) -> Vec<String> {
+    let mut spaced = String::with_capacity(input.len());
+    for c in input.chars() {
+        match CHAIN_SEPARATORS.contains(&c) {
+            true => spaced.extend([' ', c, ' ']),
+            false => spaced.push(c),
+        }
+    }
+
+    spaced
+        .split_whitespace()
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Parses a command, or an alias if the input isn't a command but uses one of `alias_words`.
+/// Only input that parses as a movement with its alias words standing in for a button is an
+/// alias, so chat that merely mentions one isn't.
+pub fn parse_command_with_aliases(input: &str, alias_words: &HashSet<String>) -> Option<Command> {
+    parse_command(input).or_else(|| {
+        let tokens = alias_tokens(input);
+        if !tokens.iter().any(|token| alias_words.contains(token)) {
+            return None;
+        }
+
+        let stand_in: Vec<&str> = tokens
+            .iter()
+            .map(|token| match alias_words.contains(token) {
+                true => "a",
+                false => token.as_str(),
+            })
+            .collect();
+        matches!(
+            parse_command(&stand_in.join(" ")),
+            Some(Command::Movement(_))
+        )
+        .then(|| Command::Alias(input.trim().to_lowercase()))
     })
 }
 
+/// Replaces alias words with the movements they stand for, aliases can't refer to other aliases
+fn resolve_aliases(input: &str, aliases: &BTreeMap<String, String>) -> Option<Command> {
+    let tokens = alias_tokens(input);
+    let expanded: Vec<&str> = tokens
+        .iter()
+        .map(|token| aliases.get(token).map_or(token.as_str(), String::as_str))
+        .collect();
+
+    match parse_command(&expanded.join(" ")) {
+        Some(command @ Command::Movement(_)) => Some(command),
+        _ => None,
+    }
+}
+
 pub fn parse_command(input: &str) -> Option<Command> {
     let mut tokens: Vec<String> = input.split_whitespace().map(|t| t.to_lowercase()).collect();
     tokens.retain(|token| *token != "\u{e0000}");
@@ -228,6 +743,9 @@ pub fn parse_command(input: &str) -> Option<Command> {
     }
 
     match &tokens[..] {
+        ["hold", buttons @ ..] => parse_buttons(buttons).map(Command::Hold),
+        ["release", "all"] => Some(Command::ReleaseAll),
+        ["release", buttons @ ..] => parse_buttons(buttons).map(Command::Release),
         ["tp", "block"] => Some(Command::Partial(PartialCommand::Block)),
         ["tp", "block", target] => Some(Command::Block(target.to_string(), None)),
         ["tp", "block", target, duration] => duration_str::parse(duration)
@@ -242,21 +760,42 @@ pub fn parse_command(input: &str) -> Option<Command> {
         ["tp", "op", target] => Some(Command::AddOperator(target.to_string())),
         ["tp", "deop"] => Some(Command::Partial(PartialCommand::RemoveOperator)),
         ["tp", "deop", target] => Some(Command::RemoveOperator(target.to_string())),
-        ["tp", "games"] => Some(Command::ListGames),
+        ["tp", "games"] => Some(Command::ListGames(None)),
+        ["tp", "games", tag @ ..] => Some(Command::ListGames(Some(tag.join(" ")))),
         ["tp", "game" | "switch" | "start"] => Some(Command::Partial(PartialCommand::Game)),
+        ["tp", "game", "search"] => Some(Command::Partial(PartialCommand::GameSearch)),
+        ["tp", "game", "search", text @ ..] => Some(Command::SearchGames(text.join(" "))),
+        ["tp", "game", "random"] => Some(Command::RandomGame(None)),
+        ["tp", "game", "random", tag @ ..] => Some(Command::RandomGame(Some(tag.join(" ")))),
         ["tp", "game" | "switch" | "start", game @ ..] => {
             let game: GameName = game.join(" ");
             Some(Command::Game(game))
         }
         ["tp", "stop"] => Some(Command::Stop),
         ["tp", "list"] => Some(Command::Partial(PartialCommand::List)),
-        ["tp", "list", "games"] => Some(Command::ListGames),
+        ["tp", "list", "games"] => Some(Command::ListGames(None)),
         ["tp", "help" | "commands"] => Some(Command::PrintHelp),
         ["tp", "list", "block" | "blocks" | "blocked"] => Some(Command::ListBlocked),
         ["tp", "list", "ops" | "operators" | "op"] => Some(Command::ListOperators),
-        ["tp", "save"] => Some(Command::SaveState),
-        ["tp", "load"] => Some(Command::LoadState),
-        ["tp", "reset"] => Some(Command::Reset),
+        ["tp", "save"] => Some(Command::Emulator(EmulatorAction::SaveState)),
+        ["tp", "load"] => Some(Command::Emulator(EmulatorAction::LoadState)),
+        ["tp", "reset"] => Some(Command::Emulator(EmulatorAction::Reset)),
+        ["tp", "save", slot] => Some(Command::SaveSlot(SaveSlot::parse(slot))),
+        ["tp", "load", slot] => Some(Command::LoadSlot(SaveSlot::parse(slot))),
+        ["tp", "slots"] => Some(Command::ListSlots),
+        ["tp", "autosave"] => Some(Command::PrintAutosave),
+        ["tp", "status"] => Some(Command::PrintGameStatus),
+        ["tp", "log"] => Some(Command::GameLog(DEFAULT_GAME_LOG_LINES)),
+        ["tp", "log", lines] => str::parse(lines)
+            .ok()
+            .filter(|lines| (1..=MAX_GAME_LOG_LINES).contains(lines))
+            .map(Command::GameLog)
+            .or(Some(Command::Partial(PartialCommand::GameLog))),
+        ["tp", "pause" | "unpause"] => Some(Command::Emulator(EmulatorAction::Pause)),
+        ["tp", "slot", "next" | "+"] => Some(Command::Emulator(EmulatorAction::StateSlotPlus)),
+        ["tp", "slot", "prev" | "previous" | "-"] => {
+            Some(Command::Emulator(EmulatorAction::StateSlotMinus))
+        }
         ["tp", "mode"] => Some(Command::PrintAnarchyMode),
         ["tp", "mode", "anarchy"] => Some(Command::SetAnarchyMode(AnarchyType::Anarchy)),
         ["tp", "mode", "democracy"] => Some(Command::SetAnarchyMode(AnarchyType::Democracy)),
@@ -264,6 +803,7 @@ pub fn parse_command(input: &str) -> Option<Command> {
         ["tp", "mode", "stream" | "streaming"] => {
             Some(Command::SetAnarchyMode(AnarchyType::Streaming))
         }
+        ["tp", "mode", "vote" | "voting"] => Some(Command::SetAnarchyMode(AnarchyType::Voting)),
         ["tp", "mode", _] => Some(Command::Partial(PartialCommand::SetAnarchyMode)),
         ["tp", "cooldown"] => Some(Command::Partial(PartialCommand::SetCooldown)),
         ["tp", "cooldown", cd] => duration_str::parse(cd)
@@ -275,135 +815,1180 @@ pub fn parse_command(input: &str) -> Option<Command> {
         ["tp", "sfx", sfx] => Some(Command::PlaySfx(sfx.to_string())),
         ["tp", "controls"] => Some(Command::Controls(None)),
         ["tp", "controls", game @ ..] => Some(Command::Controls(Some(game.join(" ")))),
+        ["tp", "voting"] => Some(Command::PrintVoteSettings),
+        ["tp", "voting", "window", window] => duration_str::parse(window)
+            .ok()
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+            .filter(|d| !d.is_zero())
+            .map(Command::SetVoteWindow)
+            .or(Some(Command::Partial(PartialCommand::VoteSettings))),
+        ["tp", "voting", "threshold", min_votes] => str::parse(min_votes)
+            .ok()
+            .map(Command::SetVoteMinVotes)
+            .or(Some(Command::Partial(PartialCommand::VoteSettings))),
+        ["tp", "voting", "ties", tie_break] => TieBreak::from_str(tie_break)
+            .map(Command::SetVoteTieBreak)
+            .or(Some(Command::Partial(PartialCommand::VoteSettings))),
+        ["tp", "voting", ..] => Some(Command::Partial(PartialCommand::VoteSettings)),
+        ["tp", "vote", "anarchy"] => Some(Command::MeterVote(MeterVote::Anarchy)),
+        ["tp", "vote", "democracy"] => Some(Command::MeterVote(MeterVote::Democracy)),
+        ["tp", "vote", ..] => Some(Command::Partial(PartialCommand::MeterVote)),
+        ["tp", "meter"] => Some(Command::PrintAnarchyMode),
+        ["tp", "meter", "lock"] => Some(Command::LockMeter(true)),
+        ["tp", "meter", "unlock"] => Some(Command::LockMeter(false)),
+        ["tp", "meter", ..] => Some(Command::Partial(PartialCommand::Meter)),
+        ["tp", "nominate"] => Some(Command::Partial(PartialCommand::Nominate)),
+        ["tp", "nominate", game @ ..] => Some(Command::Nominate(game.join(" "))),
+        ["tp", "votegame", option] => str::parse(option)
+            .ok()
+            .filter(|option| *option > 0)
+            .map(Command::VoteGame)
+            .or(Some(Command::Partial(PartialCommand::VoteGame))),
+        ["tp", "votegame", ..] => Some(Command::Partial(PartialCommand::VoteGame)),
+        ["tp", "gamepoll"] => Some(Command::PrintGamePoll),
+        ["tp", "gamepoll", "start"] => Some(Command::StartGamePoll(None)),
+        ["tp", "gamepoll", "start", tag @ ..] => Some(Command::StartGamePoll(Some(tag.join(" ")))),
+        ["tp", "gamepoll", "cancel"] => Some(Command::CancelGamePoll),
+        ["tp", "gamepoll", "window", window] => duration_str::parse(window)
+            .ok()
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+            .filter(|d| !d.is_zero())
+            .map(Command::SetGamePollWindow)
+            .or(Some(Command::Partial(PartialCommand::GamePoll))),
+        ["tp", "gamepoll", "threshold", min_votes] => str::parse(min_votes)
+            .ok()
+            .map(Command::SetGamePollMinVotes)
+            .or(Some(Command::Partial(PartialCommand::GamePoll))),
+        ["tp", "gamepoll", "options", options] => str::parse(options)
+            .ok()
+            .filter(|options| (2..=MAX_GAME_POLL_OPTIONS).contains(options))
+            .map(Command::SetGamePollOptions)
+            .or(Some(Command::Partial(PartialCommand::GamePoll))),
+        ["tp", "gamepoll", ..] => Some(Command::Partial(PartialCommand::GamePoll)),
+        ["tp", "schedule"] => Some(Command::PrintSchedule),
+        ["tp", "schedule", "skip"] => Some(Command::SkipScheduledSlot),
+        ["tp", "schedule", "override"] => Some(Command::OverrideScheduledSlot),
+        ["tp", "schedule", ..] => Some(Command::Partial(PartialCommand::Schedule)),
         _ => None,
     }
 }
 
-pub async fn run_commands(
-    rx: &mut Receiver<WithReply<Message, Option<String>>>,
-    config: &Config,
-    gamepad_tx: Sender<MovementPacket>,
-    db_conn: &mut Connection,
-    game_runner_tx: &mut Sender<game_runner::GameRunner>,
-    mut sfx_player_tx: Option<&mut UnboundedSender<SfxRequest>>,
-) -> anyhow::Result<()> {
-    let game_commands = config.game_command_list();
-    let mut current_game: Option<&ConstructedGameInfo> = None;
+/// Display names of games for a chat reply, cut short with a count of the rest if too long
+fn game_list_reply(games: &[&ConstructedGameInfo]) -> String {
+    let mut names: Vec<&str> = games
+        .iter()
+        .map(|game| game.display_name.as_str())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
 
-    let anarchy_mode = database::get_or_set_kv(
-        db_conn,
-        CONFIG_KV_ANARCHY_MODE,
-        AnarchyType::Democracy.to_str().to_owned(),
-    )?;
+    let reply = names.join(", ");
+    if reply.len() <= MAX_REPLY_LEN {
+        return reply;
+    }
 
-    let mut anarchy_mode = match AnarchyType::from_str(&anarchy_mode) {
-        Some(am) => am,
-        None => {
-            tracing::warn!(
-                "Invalid anarchy_mode {} in database, defaulting to democracy",
-                anarchy_mode
-            );
-            database::set_kv(
-                db_conn,
-                CONFIG_KV_ANARCHY_MODE,
-                AnarchyType::Democracy.to_str(),
-            )?;
-            AnarchyType::Democracy
+    let more = |shown: usize| {
+        format!(
+            " and {} more, narrow it down with tp games <tag> or tp game search <text>",
+            names.len() - shown
+        )
+    };
+    let max_len = MAX_REPLY_LEN.saturating_sub(more(0).len());
+    let mut reply = String::new();
+    let mut shown = 0;
+    for name in names.iter() {
+        if reply.len() + name.len() + 2 > max_len {
+            break;
+        }
+        if shown > 0 {
+            reply.push_str(", ");
         }
+        reply.push_str(name);
+        shown += 1;
+    }
+
+    reply + &more(shown)
+}
+
+/// The last lines of output from the game's previous session, or the session that just
+/// ended if the game isn't running
+async fn game_log_reply(
+    config: &Config,
+    game: Option<&ConstructedGameInfo>,
+    running: bool,
+    lines: usize,
+) -> anyhow::Result<String> {
+    let (game_logs, game) = match (config.game_logs(), game) {
+        (None, _) => return Ok("Game logs are disabled".to_owned()),
+        (_, None) => return Ok("No game has been started yet".to_owned()),
+        (Some(game_logs), Some(game)) => (game_logs, game),
     };
 
-    // Disable SFX if it should be disabled
-    if !matches!(anarchy_mode, AnarchyType::Streaming) {
-        if let Some(ref mut sfx_player) = sfx_player_tx {
-            sfx_player
-                .send(SfxRequest::Enable(false))
-                .expect("Failed to reinit SFX");
+    let tail = match game_log::previous_session_tail(game_logs, &game.name, running, lines).await {
+        Ok(tail) => tail,
+        Err(e) => {
+            tracing::warn!("Failed to read game log for {}: {:?}", game.name, e);
+            return Ok(format!("Failed to read the log for {}", game.name));
         }
-    }
+    };
 
-    let cooldown: String =
-        database::get_or_set_kv(db_conn, CONFIG_KV_COOLDOWN_DURATION, "0".to_owned())?;
-    let cooldown = match str::parse(&cooldown) {
-        Ok(cd) => cd,
-        Err(_) => {
-            tracing::warn!("Invalid cooldown {} in database, defaulting to 0", cooldown);
-            database::set_kv(db_conn, CONFIG_KV_COOLDOWN_DURATION, "0")?;
-            0
+    let reply = match tail {
+        Some(tail) if !tail.is_empty() => {
+            let prefix = format!("Previous session of {}: ", game.name);
+            let output = tail.join(" | ");
+            let max_len = MAX_REPLY_LEN.saturating_sub(prefix.len());
+            let skip = output.chars().count().saturating_sub(max_len);
+            match skip {
+                0 => prefix + &output,
+                skip => prefix + "..." + &output.chars().skip(skip + 3).collect::<String>(),
+            }
         }
+        Some(_) => format!("The previous session of {} had no output", game.name),
+        None => format!("No previous session of {} was logged", game.name),
     };
 
-    let mut cooldown = chrono::Duration::milliseconds(cooldown);
+    Ok(reply)
+}
 
-    while let Some(msg) = rx.recv().await {
-        use Command::*;
+/// Reads a setting from config_kv, resetting it to the default if it cannot be parsed
+fn get_or_reset_kv<T: std::str::FromStr + ToString>(
+    db_conn: &mut Connection,
+    key: &str,
+    default: T,
+) -> anyhow::Result<T> {
+    let value: String = database::get_or_set_kv(db_conn, key, default.to_string())?;
+    match str::parse(&value) {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            tracing::warn!(
+                "Invalid {} {} in database, defaulting to {}",
+                key,
+                value,
+                default.to_string()
+            );
+            database::set_kv(db_conn, key, default.to_string())?;
+            Ok(default)
+        }
+    }
+}
 
-        let reply_tx = msg.reply_tx;
-        let msg = msg.message;
+fn frames_to_millis(frames: u64, fps: u64) -> u64 {
+    ((frames * 1000 + fps / 2) / fps.max(1)).min(MAX_DURATION_MS)
+}
 
-        database::update_user(db_conn, &msg.sender_id, &msg.sender_name)
-            .context("Failed to update user")?;
+/// Converts durations given in frames into milliseconds at the game's frame rate
+fn resolve_frames(packet: &mut MovementPacket, fps: u64) {
+    if let Some(frames) = packet.frames.take() {
+        packet.duration = frames_to_millis(frames, fps);
+        packet.repeat = match packet.repeat {
+            Some(Repeat::For(_)) => Some(Repeat::For(packet.duration)),
+            Some(Repeat::Turbo(_)) => Some(Repeat::Turbo(packet.duration)),
+            repeat => repeat,
+        };
+    }
 
-        let msg = if msg.privilege < Privilege::Operator
-            && database::is_operator(db_conn, &msg.sender_id)
-                .context("Failed to check for operator")?
-        {
-            Message {
-                sender_name: msg.sender_name,
-                sender_id: msg.sender_id,
-                command: msg.command,
-                privilege: Privilege::Operator,
-            }
-        } else {
-            msg
+    for step in packet.chain.iter_mut() {
+        resolve_frames(step, fps);
+    }
+}
+
+/// Sets how long repeated presses last and caps how many there are
+fn limit_repeats(packet: &mut MovementPacket, limits: RepeatLimits) {
+    if let Some(repeat) = packet.repeat {
+        let (presses, rate) = match repeat {
+            Repeat::Times(presses) => (presses, limits.rate),
+            Repeat::For(duration) => (duration * limits.rate / 1000, limits.rate),
+            Repeat::Turbo(duration) => (duration * limits.turbo_rate / 1000, limits.turbo_rate),
         };
+        packet.repeat = Some(Repeat::Times(presses.clamp(1, limits.max_presses)));
 
-        if msg.privilege < Privilege::Operator && matches!(anarchy_mode, AnarchyType::Restricted) {
-            reply_tx
-                .send(None)
-                .map_err(|_| anyhow!("Failed to reply to command"))?;
-            continue;
-        }
+        // Held for half of each press and released for the other half
+        packet.duration = (500 / rate).max(1);
+    }
 
-        if msg.privilege < Privilege::Operator
-            && matches!(anarchy_mode, AnarchyType::Democracy)
-            && !cooldown.is_zero()
-            && !database::test_and_set_cooldown_lapsed(db_conn, &msg.sender_id, &cooldown)?
-        {
-            reply_tx
-                .send(None)
-                .map_err(|_| anyhow!("Failed to reply to command"))?;
-            continue;
+    for step in packet.chain.iter_mut() {
+        limit_repeats(step, limits);
+    }
+}
+
+/// How long a packet takes to play with its chain, in milliseconds, each repeated press is
+/// followed by a pause as long as the press
+fn play_time(packet: &MovementPacket) -> u64 {
+    let presses = match packet.repeat {
+        Some(Repeat::Times(presses)) => presses,
+        Some(Repeat::For(duration) | Repeat::Turbo(duration)) => {
+            duration / (packet.duration * 2).max(1)
         }
+        None => 1,
+    };
 
-        match msg.command {
-            SetAnarchyMode(am) => {
-                if msg.privilege >= Privilege::Moderator {
-                    // If we are in streaming mode already, disable sfx
-                    if matches!(anarchy_mode, AnarchyType::Streaming)
-                        && !matches!(am, AnarchyType::Streaming)
+    packet.chain.iter().map(play_time).fold(
+        packet
+            .duration
+            .saturating_mul(presses.max(1).saturating_mul(2) - 1),
+        u64::saturating_add,
+    )
+}
+
+fn format_tally(poll: &Poll<MovementPacket>) -> String {
+    let tally: Vec<String> = poll
+        .tally()
+        .into_iter()
+        .take(5)
+        .map(|(packet, count)| format!("{} ({})", packet, count))
+        .collect();
+    tally.join(", ")
+}
+
+/// A poll for the next game to play, voted on by option number
+struct GamePoll<'a> {
+    poll: Poll<usize>,
+    options: Vec<&'a ConstructedGameInfo>,
+}
+
+impl<'a> GamePoll<'a> {
+    fn new(window: std::time::Duration, options: Vec<&'a ConstructedGameInfo>) -> Self {
+        GamePoll {
+            poll: Poll::new(window),
+            options,
+        }
+    }
+
+    /// Numbered options with their votes so far
+    fn format_options(&self) -> String {
+        let tally = self.poll.tally();
+        let options: Vec<String> = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(idx, game)| {
+                let votes = tally
+                    .iter()
+                    .find(|(option, _)| **option == idx)
+                    .map_or(0, |(_, votes)| *votes);
+                format!("{} {} ({})", idx + 1, game.display_name, votes)
+            })
+            .collect();
+        options.join(", ")
+    }
+
+    fn format_tally(&self) -> String {
+        let tally: Vec<String> = self
+            .poll
+            .tally()
+            .into_iter()
+            .take(5)
+            .map(|(option, count)| format!("{} ({})", self.options[*option].display_name, count))
+            .collect();
+        tally.join(", ")
+    }
+}
+
+/// A game change picked by a command, poll or the schedule
+enum GameChange<'a> {
+    SwitchTo(&'a ConstructedGameInfo),
+    Stop,
+}
+
+/// Up to `count` games picked at random
+fn random_games(mut games: Vec<&ConstructedGameInfo>, count: usize) -> Vec<&ConstructedGameInfo> {
+    let mut picked = Vec::new();
+    while picked.len() < count && !games.is_empty() {
+        picked.push(games.swap_remove(vote::random_index(games.len())));
+    }
+    picked
+}
+
+/// Finds the slot number for a numbered or named slot, new names get the lowest free slot
+/// when saving. The inner error is a reply for chat.
+fn resolve_slot(
+    game: &ConstructedGameInfo,
+    slot: &SaveSlot,
+    save: bool,
+    db_conn: &Connection,
+) -> anyhow::Result<Result<u64, String>> {
+    let slot_num = match slot {
+        SaveSlot::Number(slot_num) => *slot_num,
+        SaveSlot::Name(name) => match database::find_save_slot(db_conn, &game.name, name)? {
+            Some(slot_num) => slot_num,
+            None if save => {
+                let saved_slots = database::list_save_slots(db_conn, &game.name)?;
+
+                // Slot 0 is left for unnamed saves
+                match (1..game.save_slots.count)
+                    .find(|slot_num| !saved_slots.iter().any(|info| info.slot == *slot_num))
+                {
+                    Some(slot_num) => slot_num,
+                    None => {
+                        return Ok(Err(
+                            "No free save slots left, overwrite one instead".to_owned()
+                        ))
+                    }
+                }
+            }
+            None => return Ok(Err(format!("No save named {}, see \"tp slots\"", name))),
+        },
+    };
+
+    if slot_num >= game.save_slots.count {
+        return Ok(Err(format!(
+            "Save slots are numbered 0 to {}",
+            game.save_slots.count.saturating_sub(1)
+        )));
+    }
+
+    Ok(Ok(slot_num))
+}
+
+/// Selects the slot then saves or loads it, recording saves in the database. The inner
+/// error is a reply for chat.
+#[allow(clippy::too_many_arguments)]
+async fn run_slot_action(
+    game: &ConstructedGameInfo,
+    slot_num: u64,
+    name: Option<&str>,
+    action: EmulatorAction,
+    saved_by: &str,
+    state_slot: &mut u64,
+    db_conn: &mut Connection,
+    gamepad_tx: &Sender<MovementPacket>,
+) -> anyhow::Result<Result<(), String>> {
+    if let Err(reply) = game
+        .emulator
+        .select_slot(*state_slot, slot_num, gamepad_tx)
+        .await?
+    {
+        return Ok(Err(reply));
+    }
+    *state_slot = slot_num;
+
+    if let Err(reply) = game.emulator.run(action, gamepad_tx).await? {
+        return Ok(Err(reply));
+    }
+
+    info!("{} ran {} on slot {}", saved_by, action.to_str(), slot_num);
+    if action == EmulatorAction::SaveState {
+        database::record_save_slot(db_conn, &game.name, slot_num, name, saved_by)?;
+    }
+
+    Ok(Ok(()))
+}
+
+/// Saves or loads a numbered or named slot for the current game, returning the reply for chat
+async fn run_slot_command(
+    game: &ConstructedGameInfo,
+    slot: &SaveSlot,
+    action: EmulatorAction,
+    msg: &Message,
+    state_slot: &mut u64,
+    db_conn: &mut Connection,
+    gamepad_tx: &Sender<MovementPacket>,
+) -> anyhow::Result<String> {
+    let save = action == EmulatorAction::SaveState;
+    let slot_num = match resolve_slot(game, slot, save, db_conn)? {
+        Ok(slot_num) => slot_num,
+        Err(reply) => return Ok(reply),
+    };
+
+    let required = if !save {
+        game.save_slots.load_privilege(slot_num)
+    } else if database::list_save_slots(db_conn, &game.name)?
+        .iter()
+        .any(|info| info.slot == slot_num)
+    {
+        game.save_slots.overwrite_privilege(slot_num)
+    } else {
+        Privilege::Operator
+    };
+
+    if msg.privilege < required {
+        info!(
+            "{} attempted to {} slot {} with insufficient privilege {:?}",
+            msg.sender_name,
+            action.to_str(),
+            slot_num,
+            msg.privilege
+        );
+        return Ok("You don't have permission to do that".to_owned());
+    }
+
+    let name = match slot {
+        SaveSlot::Name(name) => Some(name.as_str()),
+        SaveSlot::Number(_) => None,
+    };
+
+    if let Err(reply) = run_slot_action(
+        game,
+        slot_num,
+        name,
+        action,
+        &msg.sender_name,
+        state_slot,
+        db_conn,
+        gamepad_tx,
+    )
+    .await?
+    {
+        return Ok(reply);
+    }
+
+    let slot_name = match name {
+        Some(name) => format!("{} ({})", slot_num, name),
+        None => slot_num.to_string(),
+    };
+
+    if save {
+        Ok(format!("Saved game state to slot {}", slot_name))
+    } else {
+        Ok(format!("Loaded game state from slot {}", slot_name))
+    }
+}
+
+/// Saves the current game to its auto-save slot, failures are only logged
+async fn autosave(
+    game: &ConstructedGameInfo,
+    state_slot: &mut u64,
+    db_conn: &mut Connection,
+    gamepad_tx: &Sender<MovementPacket>,
+) -> anyhow::Result<()> {
+    let slot = SaveSlot::Name(AUTOSAVE_SLOT_NAME.to_owned());
+    let result = match resolve_slot(game, &slot, true, db_conn)? {
+        Ok(slot_num) => {
+            run_slot_action(
+                game,
+                slot_num,
+                Some(AUTOSAVE_SLOT_NAME),
+                EmulatorAction::SaveState,
+                AUTOSAVE_SLOT_NAME,
+                state_slot,
+                db_conn,
+                gamepad_tx,
+            )
+            .await?
+        }
+        Err(reply) => Err(reply),
+    };
+
+    if let Err(reason) = result {
+        tracing::warn!("Failed to auto-save {}: {}", game.name, reason);
+    }
+
+    Ok(())
+}
+
+/// Loads the most recent auto-save of a game that was resumed at startup
+async fn load_autosave(
+    game: &ConstructedGameInfo,
+    state_slot: &mut u64,
+    db_conn: &mut Connection,
+    gamepad_tx: &Sender<MovementPacket>,
+) -> anyhow::Result<()> {
+    let slot_num = match database::find_save_slot(db_conn, &game.name, AUTOSAVE_SLOT_NAME)? {
+        Some(slot_num) => slot_num,
+        None => {
+            info!("No auto-save to load for {}", game.name);
+            return Ok(());
+        }
+    };
+
+    let result = run_slot_action(
+        game,
+        slot_num,
+        Some(AUTOSAVE_SLOT_NAME),
+        EmulatorAction::LoadState,
+        AUTOSAVE_SLOT_NAME,
+        state_slot,
+        db_conn,
+        gamepad_tx,
+    )
+    .await?;
+
+    if let Err(reason) = result {
+        tracing::warn!("Failed to load auto-save for {}: {}", game.name, reason);
+    }
+
+    Ok(())
+}
+
+/// Auto-saves a game that's about to be stopped, the game runner waits for the emulator to
+/// finish saving before stopping it
+async fn autosave_before_stop(
+    game: Option<&ConstructedGameInfo>,
+    state_slot: &mut u64,
+    db_conn: &mut Connection,
+    gamepad_tx: &Sender<MovementPacket>,
+    game_runner_tx: &mut Sender<GameRunner>,
+) -> anyhow::Result<()> {
+    if let Some(game) = game.filter(|game| game.autosave_interval.is_some()) {
+        autosave(game, state_slot, db_conn, gamepad_tx).await?;
+        game_runner_tx
+            .send(GameRunner::DelayStop(AUTOSAVE_BEFORE_STOP_DELAY))
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn wait_until(deadline: Option<tokio::time::Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(deadline).await;
+    } else {
+        // Never resolve so that we don't spin excessively
+        std::future::pending::<()>().await;
+    }
+}
+
+/// When the schedule should next be checked, `None` if there's nothing scheduled
+fn next_schedule_check(slots: &[ScheduleSlot]) -> Option<tokio::time::Instant> {
+    let now = chrono::Local::now().naive_local();
+    let next = schedule::next_change(slots, now)?;
+    let wait = (next - now).to_std().unwrap_or_default();
+    Some(tokio::time::Instant::now() + wait.min(MAX_SCHEDULE_CHECK_INTERVAL))
+}
+
+fn print_schedule(slots: &[ScheduleSlot]) -> String {
+    if slots.is_empty() {
+        return "No schedule is configured".to_owned();
+    }
+
+    let now = chrono::Local::now().naive_local();
+    let current = match schedule::active_at(slots, now) {
+        Some(run) => format!(
+            "Now: {} until {}",
+            slots[run.slot].describe(),
+            run.end.format("%H:%M")
+        ),
+        None => "Nothing is scheduled now".to_owned(),
+    };
+
+    let upcoming: Vec<String> = schedule::upcoming(slots, now, SCHEDULE_UPCOMING_SLOTS)
+        .iter()
+        .map(|run| {
+            format!(
+                "{} {}",
+                run.start.format("%a %H:%M"),
+                slots[run.slot].describe()
+            )
+        })
+        .collect();
+    match upcoming.is_empty() {
+        true => current,
+        false => format!("{}, next: {}", current, upcoming.join(", ")),
+    }
+}
+
+async fn close_movement_vote(
+    poll: &Poll<MovementPacket>,
+    tie_break: TieBreak,
+    min_votes: u64,
+    gamepad_tx: &Sender<MovementPacket>,
+    announce_tx: &UnboundedSender<String>,
+) -> anyhow::Result<()> {
+    let announcement = match poll.result(tie_break, min_votes) {
+        PollResult::Winner(packet) => {
+            info!("Vote won by {:?}", packet);
+            let announcement = format!("Vote result: {}", format_tally(poll));
+            gamepad_tx.send(packet).await?;
+            announcement
+        }
+        PollResult::Tied => format!("Vote tied, no input sent: {}", format_tally(poll)),
+        PollResult::NotEnoughVotes(votes) => {
+            format!("Vote failed, {} of {} required votes", votes, min_votes)
+        }
+    };
+
+    announce_tx
+        .send(announcement)
+        .map_err(|_| anyhow!("Failed to send announcement"))?;
+    Ok(())
+}
+
+/// Announces the result of a game poll, returning the game to switch to if it won
+fn close_game_poll<'a>(
+    poll: &GamePoll<'a>,
+    min_votes: u64,
+    current_game: Option<&ConstructedGameInfo>,
+    announce_tx: &UnboundedSender<String>,
+) -> anyhow::Result<Option<&'a ConstructedGameInfo>> {
+    let (announcement, winner) = match poll.poll.result(TieBreak::Random, min_votes) {
+        PollResult::Winner(option) => {
+            let game = poll.options[option];
+            if current_game.map(|current| &current.name) == Some(&game.name) {
+                (
+                    format!(
+                        "Game vote result: {}, staying on {}",
+                        poll.format_tally(),
+                        game.display_name
+                    ),
+                    None,
+                )
+            } else {
+                (
+                    format!(
+                        "Game vote result: {}, switching to {}",
+                        poll.format_tally(),
+                        game.display_name
+                    ),
+                    Some(game),
+                )
+            }
+        }
+        PollResult::Tied => (format!("Game vote tied: {}", poll.format_tally()), None),
+        PollResult::NotEnoughVotes(votes) => (
+            format!(
+                "Game vote failed, {} of {} required votes",
+                votes, min_votes
+            ),
+            None,
+        ),
+    };
+
+    info!("{}", announcement);
+    announce_tx
+        .send(announcement)
+        .map_err(|_| anyhow!("Failed to send announcement"))?;
+    Ok(winner)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run_commands(
+    rx: &mut Receiver<WithReply<Message, Option<String>>>,
+    config: &Config,
+    gamepad_tx: Sender<MovementPacket>,
+    db_conn: &mut Connection,
+    game_runner_tx: &mut Sender<game_runner::GameRunner>,
+    game_event_rx: &mut UnboundedReceiver<GameEvent>,
+    mut sfx_player_tx: Option<&mut UnboundedSender<SfxRequest>>,
+    announce_tx: UnboundedSender<String>,
+) -> anyhow::Result<()> {
+    let game_commands = config.game_command_list();
+    let global_emulator = config.global_emulator_control();
+    let global_repeat_limits = config.global_repeat_limits();
+    let global_holds = config.holds.clone().unwrap_or_default();
+    let global_aliases = config.global_aliases();
+    let mut current_game: Option<&ConstructedGameInfo> = None;
+    // Kept after the game exits so its log can still be read
+    let mut last_game: Option<&ConstructedGameInfo> = None;
+
+    // The emulator's selected state slot, assumed to start at 0 for each game
+    let mut state_slot: u64 = 0;
+    let mut autosave_at: Option<tokio::time::Instant> = None;
+
+    // Set while the current game hasn't passed its readiness check yet
+    let mut game_loading = false;
+    let mut held_movements: Vec<MovementPacket> = Vec::new();
+
+    let anarchy_mode = database::get_or_set_kv(
+        db_conn,
+        CONFIG_KV_ANARCHY_MODE,
+        AnarchyType::Democracy.to_str().to_owned(),
+    )?;
+
+    let mut anarchy_mode = match AnarchyType::from_str(&anarchy_mode) {
+        Some(am) => am,
+        None => {
+            tracing::warn!(
+                "Invalid anarchy_mode {} in database, defaulting to democracy",
+                anarchy_mode
+            );
+            database::set_kv(
+                db_conn,
+                CONFIG_KV_ANARCHY_MODE,
+                AnarchyType::Democracy.to_str(),
+            )?;
+            AnarchyType::Democracy
+        }
+    };
+
+    // Disable SFX if it should be disabled
+    if !matches!(anarchy_mode, AnarchyType::Streaming) {
+        if let Some(ref mut sfx_player) = sfx_player_tx {
+            sfx_player
+                .send(SfxRequest::Enable(false))
+                .expect("Failed to reinit SFX");
+        }
+    }
+
+    let cooldown: String =
+        database::get_or_set_kv(db_conn, CONFIG_KV_COOLDOWN_DURATION, "0".to_owned())?;
+    let cooldown = match str::parse(&cooldown) {
+        Ok(cd) => cd,
+        Err(_) => {
+            tracing::warn!("Invalid cooldown {} in database, defaulting to 0", cooldown);
+            database::set_kv(db_conn, CONFIG_KV_COOLDOWN_DURATION, "0")?;
+            0
+        }
+    };
+
+    let mut cooldown = chrono::Duration::milliseconds(cooldown);
+
+    let vote_window = get_or_reset_kv(db_conn, CONFIG_KV_VOTE_WINDOW, DEFAULT_VOTE_WINDOW_MS)?;
+    let mut vote_window = chrono::Duration::milliseconds(vote_window);
+    let mut vote_min_votes = get_or_reset_kv(db_conn, CONFIG_KV_VOTE_MIN_VOTES, 1u64)?;
+
+    let vote_tie_break = database::get_or_set_kv(
+        db_conn,
+        CONFIG_KV_VOTE_TIE_BREAK,
+        TieBreak::First.to_str().to_owned(),
+    )?;
+    let mut vote_tie_break = match TieBreak::from_str(&vote_tie_break) {
+        Some(tb) => tb,
+        None => {
+            tracing::warn!(
+                "Invalid vote_tie_break {} in database, defaulting to first",
+                vote_tie_break
+            );
+            database::set_kv(db_conn, CONFIG_KV_VOTE_TIE_BREAK, TieBreak::First.to_str())?;
+            TieBreak::First
+        }
+    };
+
+    let mut movement_vote: Option<Poll<MovementPacket>> = None;
+
+    let game_poll_window = get_or_reset_kv(
+        db_conn,
+        CONFIG_KV_GAME_POLL_WINDOW,
+        DEFAULT_GAME_POLL_WINDOW_MS,
+    )?;
+    let mut game_poll_window = chrono::Duration::milliseconds(game_poll_window);
+    let mut game_poll_min_votes = get_or_reset_kv(
+        db_conn,
+        CONFIG_KV_GAME_POLL_MIN_VOTES,
+        DEFAULT_GAME_POLL_MIN_VOTES,
+    )?;
+    let mut game_poll_options = get_or_reset_kv(
+        db_conn,
+        CONFIG_KV_GAME_POLL_OPTIONS,
+        DEFAULT_GAME_POLL_OPTIONS,
+    )?;
+    let mut game_poll: Option<GamePoll> = None;
+    // When each viewer last opened a game poll by nominating
+    let mut game_polls_opened: HashMap<String, tokio::time::Instant> = HashMap::new();
+
+    // Changes picked by commands, polls and the schedule, applied before waiting for anything else
+    let mut set_mode: Option<AnarchyType> = None;
+    let mut game_change: Option<GameChange> = None;
+
+    let schedule: &[ScheduleSlot] = config.schedule.as_deref().unwrap_or_default();
+    for slot in schedule.iter() {
+        if let Some(ref game) = slot.game {
+            if !game_commands.contains_key(game) {
+                tracing::warn!("Scheduled game {} is not configured", game);
+            }
+        }
+    }
+
+    // The scheduled slot that last started, and whether its game is stopped when it ends
+    let schedule_slot: Option<String> =
+        database::get_or_set_kv(db_conn, CONFIG_KV_SCHEDULE_SLOT, None)?;
+    let mut schedule_run = schedule_slot.and_then(|key| SlotTime::from_key(schedule, &key));
+    let mut schedule_stop_pending =
+        get_or_reset_kv(db_conn, CONFIG_KV_SCHEDULE_STOP_PENDING, false)?;
+    let mut schedule_at = match schedule.is_empty() {
+        true => None,
+        false => Some(tokio::time::Instant::now()),
+    };
+
+    let meter_value = get_or_reset_kv(db_conn, CONFIG_KV_METER_VALUE, meter::METER_CENTER)?;
+    let meter_locked = get_or_reset_kv(db_conn, CONFIG_KV_METER_LOCKED, false)?;
+    let mut mode_meter = ModeMeter::new(meter_value, meter_locked);
+
+    // Relaunch whatever was running before we were restarted
+    let mut resume_load_at: Option<tokio::time::Instant> = None;
+    // Games with a readiness check load their auto-save once they're ready instead
+    let mut resume_on_ready = false;
+    let previous_game: Option<GameName> =
+        database::get_or_set_kv(db_conn, CONFIG_KV_CURRENT_GAME, None)?;
+    if let Some(previous_game) = previous_game {
+        match game_commands.get(&previous_game) {
+            Some(_) if matches!(anarchy_mode, AnarchyType::Streaming) => {
+                info!("Not resuming {} in streaming mode", previous_game);
+            }
+            Some(game_info) => {
+                info!("Resuming {}", previous_game);
+                current_game = Some(game_info);
+                last_game = Some(game_info);
+                game_loading = game_info.command.ready.is_some();
+                autosave_at = game_info
+                    .autosave_interval
+                    .map(|interval| tokio::time::Instant::now() + interval);
+                if game_loading {
+                    resume_on_ready = config.resume_autosave_delay.is_some();
+                } else {
+                    resume_load_at = config
+                        .resume_autosave_delay
+                        .map(|delay| tokio::time::Instant::now() + delay);
+                }
+                game_runner_tx
+                    .send(GameRunner::SwitchTo(Box::new(game_info.command.clone())))
+                    .await?;
+            }
+            None => {
+                tracing::warn!(
+                    "Previous game {} is no longer configured, not resuming it",
+                    previous_game
+                );
+                database::set_kv(db_conn, CONFIG_KV_CURRENT_GAME, None::<String>)?;
+            }
+        }
+    }
+
+    loop {
+        if let Some(am) = set_mode.take() {
+            // If we are in streaming mode already, disable sfx
+            if matches!(anarchy_mode, AnarchyType::Streaming)
+                && !matches!(am, AnarchyType::Streaming)
+            {
+                if let Some(ref mut sfx_player) = sfx_player_tx {
+                    sfx_player
+                        .send(SfxRequest::Enable(false))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+
+            anarchy_mode = am;
+            database::set_kv(db_conn, CONFIG_KV_ANARCHY_MODE, anarchy_mode.to_str())?;
+
+            if !matches!(am, AnarchyType::Voting) {
+                movement_vote = None;
+            } else {
+                // Chat can't release held buttons while voting
+                gamepad_tx.send(MovementPacket::release_all()).await?;
+            }
+
+            if let AnarchyType::Streaming = am {
+                game_poll = None;
+                game_change = Some(GameChange::Stop);
+                if let Some(ref mut sfx_player) = sfx_player_tx {
+                    sfx_player
+                        .send(SfxRequest::Enable(true))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+        }
+
+        match game_change.take() {
+            Some(GameChange::SwitchTo(game_info)) => {
+                // Saving before the game or its auto-save has loaded would overwrite the auto-save
+                autosave_before_stop(
+                    current_game.filter(|_| !game_loading && resume_load_at.is_none()),
+                    &mut state_slot,
+                    db_conn,
+                    &gamepad_tx,
+                    game_runner_tx,
+                )
+                .await?;
+                current_game = Some(game_info);
+                last_game = Some(game_info);
+                game_loading = game_info.command.ready.is_some();
+                held_movements.clear();
+                state_slot = 0;
+                autosave_at = game_info
+                    .autosave_interval
+                    .map(|interval| tokio::time::Instant::now() + interval);
+                resume_load_at = None;
+                resume_on_ready = false;
+                database::set_kv(db_conn, CONFIG_KV_CURRENT_GAME, &game_info.name)?;
+                gamepad_tx.send(MovementPacket::release_all()).await?;
+                game_runner_tx
+                    .send(GameRunner::SwitchTo(Box::new(game_info.command.clone())))
+                    .await?;
+            }
+            Some(GameChange::Stop) => {
+                // Saving before the game or its auto-save has loaded would overwrite the auto-save
+                autosave_before_stop(
+                    current_game.filter(|_| !game_loading && resume_load_at.is_none()),
+                    &mut state_slot,
+                    db_conn,
+                    &gamepad_tx,
+                    game_runner_tx,
+                )
+                .await?;
+                current_game = None;
+                game_loading = false;
+                held_movements.clear();
+                autosave_at = None;
+                resume_load_at = None;
+                resume_on_ready = false;
+                database::set_kv(db_conn, CONFIG_KV_CURRENT_GAME, None::<String>)?;
+                gamepad_tx.send(MovementPacket::release_all()).await?;
+                game_runner_tx.send(GameRunner::Stop).await?;
+            }
+            None => {}
+        }
+
+        let msg = tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = vote::wait_for_close(movement_vote.as_ref()) => {
+                if let Some(poll) = movement_vote.take() {
+                    close_movement_vote(
+                        &poll,
+                        vote_tie_break,
+                        vote_min_votes,
+                        &gamepad_tx,
+                        &announce_tx,
+                    )
+                    .await?;
+                }
+                continue;
+            }
+            _ = vote::wait_for_close(game_poll.as_ref().map(|poll| &poll.poll)) => {
+                if let Some(poll) = game_poll.take() {
+                    game_change = close_game_poll(
+                        &poll,
+                        game_poll_min_votes,
+                        current_game,
+                        &announce_tx,
+                    )?
+                    .map(GameChange::SwitchTo);
+                }
+                continue;
+            }
+            _ = wait_until(schedule_at) => {
+                schedule_at = next_schedule_check(schedule);
+                let now = chrono::Local::now().naive_local();
+                let active = schedule::active_at(schedule, now);
+                if active == schedule_run {
+                    continue;
+                }
+
+                let scheduled_game = |run: &SlotTime| schedule[run.slot].game.as_ref();
+                if let Some(ended) = schedule_run.as_ref().filter(|run| run.end <= now) {
+                    if schedule_stop_pending
+                        && current_game.map(|game| &game.name) == scheduled_game(ended)
                     {
-                        if let Some(ref mut sfx_player) = sfx_player_tx {
-                            sfx_player
-                                .send(SfxRequest::Enable(false))
-                                .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        game_change = Some(GameChange::Stop);
+                        let announcement =
+                            format!("Scheduled slot {} has ended", schedule[ended.slot].describe());
+                        info!("{}", announcement);
+                        announce_tx
+                            .send(announcement)
+                            .map_err(|_| anyhow!("Failed to send announcement"))?;
+                    }
+                }
+                schedule_stop_pending = false;
+
+                // An earlier slot that was overlapped by the one that ended isn't started again
+                let resumed = matches!(
+                    (&active, &schedule_run),
+                    (Some(active), Some(previous)) if active.start <= previous.start
+                );
+                if let Some(run) = active.as_ref().filter(|_| !resumed) {
+                    let slot = &schedule[run.slot];
+                    let mode = slot.mode.unwrap_or(anarchy_mode);
+                    if mode != anarchy_mode {
+                        set_mode = Some(mode);
+                    }
+
+                    let mut announcement = format!(
+                        "Scheduled: {} until {}",
+                        slot.describe(),
+                        run.end.format("%H:%M")
+                    );
+                    match slot.game.as_ref().and_then(|game| game_commands.get(game)) {
+                        Some(game_info) if matches!(mode, AnarchyType::Streaming) => {
+                            announcement.push_str(&format!(
+                                ", not starting {} in streaming mode",
+                                game_info.name
+                            ));
                         }
+                        Some(game_info) => {
+                            if current_game.map(|game| &game.name) != Some(&game_info.name) {
+                                game_change = Some(GameChange::SwitchTo(game_info));
+                            }
+                            schedule_stop_pending = true;
+                        }
+                        None => {}
                     }
 
-                    anarchy_mode = am;
-                    database::set_kv(db_conn, CONFIG_KV_ANARCHY_MODE, anarchy_mode.to_str())?;
+                    info!("{}", announcement);
+                    announce_tx
+                        .send(announcement)
+                        .map_err(|_| anyhow!("Failed to send announcement"))?;
+                }
 
-                    if let AnarchyType::Streaming = am {
+                schedule_run = active;
+                database::set_kv(
+                    db_conn,
+                    CONFIG_KV_SCHEDULE_SLOT,
+                    schedule_run.as_ref().map(|run| run.key()),
+                )?;
+                database::set_kv(
+                    db_conn,
+                    CONFIG_KV_SCHEDULE_STOP_PENDING,
+                    schedule_stop_pending.to_string(),
+                )?;
+                continue;
+            }
+            Some(event) = game_event_rx.recv() => {
+                // Events about a game we've already switched away from or stopped
+                if current_game.map(|game| &game.name) != Some(event.game()) {
+                    info!("Ignoring {:?} for a game that isn't running", event);
+                    continue;
+                }
+
+                let name = event.game().clone();
+                let announcement = match event {
+                    GameEvent::Exited {
+                        status,
+                        restart: Some((delay, attempt)),
+                        max_retries,
+                        ..
+                    } => format!(
+                        "{} exited ({}), restarting in {} seconds (attempt {} of {})",
+                        name,
+                        status,
+                        delay.as_secs_f64(),
+                        attempt,
+                        max_retries
+                    ),
+                    GameEvent::Exited { status, .. } => {
+                        let announcement = format!("{} exited ({})", name, status);
                         current_game = None;
-                        game_runner_tx.send(GameRunner::Stop).await?;
-                        if let Some(ref mut sfx_player) = sfx_player_tx {
-                            sfx_player
-                                .send(SfxRequest::Enable(true))
-                                .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        game_loading = false;
+                        held_movements.clear();
+                        autosave_at = None;
+                        resume_load_at = None;
+                        resume_on_ready = false;
+                        database::set_kv(db_conn, CONFIG_KV_CURRENT_GAME, None::<String>)?;
+                        announcement
+                    }
+                    GameEvent::LaunchFailed { reason, .. } => {
+                        let announcement = format!("Failed to start {}: {}", name, reason);
+                        current_game = None;
+                        game_loading = false;
+                        held_movements.clear();
+                        autosave_at = None;
+                        resume_load_at = None;
+                        resume_on_ready = false;
+                        database::set_kv(db_conn, CONFIG_KV_CURRENT_GAME, None::<String>)?;
+                        announcement
+                    }
+                    GameEvent::Restarted {
+                        attempt,
+                        max_retries,
+                        ..
+                    } => {
+                        state_slot = 0;
+                        game_loading = current_game.is_some_and(|game| game.command.ready.is_some());
+                        held_movements.clear();
+                        format!(
+                            "Restarted {} (attempt {} of {})",
+                            name, attempt, max_retries
+                        )
+                    }
+                    GameEvent::Ready { .. } => {
+                        if !game_loading {
+                            continue;
                         }
+
+                        game_loading = false;
+                        for packet in held_movements.drain(..) {
+                            info!("Sending held movement {:?}", packet);
+                            gamepad_tx.send(packet).await?;
+                        }
+                        if std::mem::take(&mut resume_on_ready) {
+                            if let Some(game) = current_game {
+                                load_autosave(game, &mut state_slot, db_conn, &gamepad_tx).await?;
+                            }
+                        }
+                        format!("{} is ready", name)
+                    }
+                };
+
+                info!("{}", announcement);
+                announce_tx
+                    .send(announcement)
+                    .map_err(|_| anyhow!("Failed to send announcement"))?;
+                continue;
+            }
+            _ = wait_until(resume_load_at) => {
+                resume_load_at = None;
+                if let Some(game) = current_game {
+                    load_autosave(game, &mut state_slot, db_conn, &gamepad_tx).await?;
+                }
+                continue;
+            }
+            _ = wait_until(autosave_at) => {
+                if game_loading || resume_load_at.is_some() {
+                    info!("Skipping auto-save until the game has loaded");
+                } else if let Some(game) = current_game {
+                    autosave(game, &mut state_slot, db_conn, &gamepad_tx).await?;
+                }
+                autosave_at = current_game
+                    .and_then(|game| game.autosave_interval)
+                    .map(|interval| tokio::time::Instant::now() + interval);
+                continue;
+            }
+        };
+
+        use Command::*;
+
+        let reply_tx = msg.reply_tx;
+        let msg = msg.message;
+
+        // Aliases mean whatever the game being played when they arrive says they do, those that
+        // don't mean anything are dropped before they're treated as commands from the user
+        let msg = match msg.command {
+            Alias(input) => {
+                let aliases = current_game.map_or(&global_aliases, |game| &game.aliases);
+                match resolve_aliases(&input, aliases) {
+                    Some(command) => Message { command, ..msg },
+                    None => {
+                        info!("{} doesn't resolve to a movement", input);
+                        reply_tx
+                            .send(None)
+                            .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        continue;
                     }
+                }
+            }
+            _ => msg,
+        };
+
+        database::update_user(db_conn, &msg.sender_id, &msg.sender_name)
+            .context("Failed to update user")?;
+
+        let msg = if msg.privilege < Privilege::Operator
+            && database::is_operator(db_conn, &msg.sender_id)
+                .context("Failed to check for operator")?
+        {
+            Message {
+                sender_name: msg.sender_name,
+                sender_id: msg.sender_id,
+                command: msg.command,
+                privilege: Privilege::Operator,
+            }
+        } else {
+            msg
+        };
+
+        if msg.privilege < Privilege::Operator && matches!(anarchy_mode, AnarchyType::Restricted) {
+            reply_tx
+                .send(None)
+                .map_err(|_| anyhow!("Failed to reply to command"))?;
+            continue;
+        }
+
+        if msg.privilege < Privilege::Operator
+            && matches!(anarchy_mode, AnarchyType::Democracy)
+            && !cooldown.is_zero()
+            && !database::test_and_set_cooldown_lapsed(db_conn, &msg.sender_id, &cooldown)?
+        {
+            reply_tx
+                .send(None)
+                .map_err(|_| anyhow!("Failed to reply to command"))?;
+            continue;
+        }
 
+        match msg.command {
+            SetAnarchyMode(am) => {
+                if msg.privilege >= Privilege::Moderator {
+                    set_mode = Some(am);
                     reply_tx
-                        .send(Some(format!("Set mode to {}", anarchy_mode.to_str())))
+                        .send(Some(format!("Set mode to {}", am.to_str())))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 } else {
                     reply_tx
@@ -413,9 +1998,75 @@ pub async fn run_commands(
             }
             PrintAnarchyMode => {
                 reply_tx
-                    .send(Some(format!("Current mode is {}", anarchy_mode.to_str())))
+                    .send(Some(format!(
+                        "Current mode is {}, anarchy meter is at {}%{}",
+                        anarchy_mode.to_str(),
+                        mode_meter.value,
+                        if mode_meter.locked { " (locked)" } else { "" }
+                    )))
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
+            MeterVote(vote) => {
+                if mode_meter.locked {
+                    reply_tx
+                        .send(Some("The anarchy meter is locked".to_owned()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
+                }
+
+                if !ModeMeter::is_active(anarchy_mode) {
+                    reply_tx
+                        .send(Some(format!(
+                            "The anarchy meter is not used in {} mode",
+                            anarchy_mode.to_str()
+                        )))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
+                }
+
+                reply_tx
+                    .send(None)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+
+                if !mode_meter.vote(&msg.sender_id, vote) {
+                    info!("{} voted on the meter too recently", msg.sender_name);
+                    continue;
+                }
+
+                info!(
+                    "{} voted {:?}, meter at {}",
+                    msg.sender_name, vote, mode_meter.value
+                );
+                database::set_kv(db_conn, CONFIG_KV_METER_VALUE, mode_meter.value)?;
+
+                if let Some(am) = mode_meter.target_mode(anarchy_mode) {
+                    info!("Meter switching mode to {}", am.to_str());
+                    set_mode = Some(am);
+                    announce_tx
+                        .send(format!(
+                            "Chat switched the mode to {}, anarchy meter is at {}%",
+                            am.to_str(),
+                            mode_meter.value
+                        ))
+                        .map_err(|_| anyhow!("Failed to send announcement"))?;
+                }
+            }
+            LockMeter(locked) => {
+                if msg.privilege >= Privilege::Moderator {
+                    mode_meter.locked = locked;
+                    database::set_kv(db_conn, CONFIG_KV_METER_LOCKED, locked.to_string())?;
+                    reply_tx
+                        .send(Some(format!(
+                            "{} the anarchy meter",
+                            if locked { "Locked" } else { "Unlocked" }
+                        )))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
             SetCooldown(cd) => {
                 if msg.privilege >= Privilege::Moderator {
                     database::set_kv(db_conn, CONFIG_KV_COOLDOWN_DURATION, cd.num_milliseconds())?;
@@ -431,21 +2082,106 @@ pub async fn run_commands(
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 }
-            }
-            Movement(packet) => {
+            }
+            Movement(mut packet) => {
+                reply_tx
+                    .send(None)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+
+                resolve_frames(
+                    &mut packet,
+                    current_game.map_or(DEFAULT_FPS, |game| game.fps),
+                );
+                limit_repeats(
+                    &mut packet,
+                    current_game.map_or(global_repeat_limits, |game| game.repeat_limits),
+                );
+
+                let chained = !packet.chain.is_empty() || packet.repeat.is_some();
+                if chained && play_time(&packet) > MAX_PLAY_TIME_MS {
+                    info!("Packet takes too long to play {:?}", packet);
+                    continue;
+                }
+
+                if !matches!(anarchy_mode, AnarchyType::Restricted)
+                    && current_game.is_some_and(|game| game.is_movement_restricted(&packet))
+                {
+                    info!("Packet contains restricted movement {:?}", packet);
+                    continue;
+                }
+
+                if matches!(anarchy_mode, AnarchyType::Streaming) {
+                    info!("Mode is streaming, skipping movement");
+                    continue;
+                }
+
+                if matches!(anarchy_mode, AnarchyType::Anarchy)
+                    || !database::is_blocked(db_conn, &msg.sender_id)
+                        .context("Failed to check for blocked user")?
+                {
+                    if game_loading {
+                        let hold = current_game
+                            .is_some_and(|game| game.while_loading == LoadingInput::Hold);
+                        if hold && held_movements.len() < MAX_HELD_MOVEMENTS {
+                            info!("Game is loading, holding movement {:?}", packet);
+                            held_movements.push(packet);
+                        } else {
+                            info!("Game is loading, discarding movement {:?}", packet);
+                        }
+                    } else if matches!(anarchy_mode, AnarchyType::Voting) {
+                        info!("{} voted for {:?}", msg.sender_name, packet);
+                        let window = vote_window.to_std()?;
+                        movement_vote
+                            .get_or_insert_with(|| Poll::new(window))
+                            .vote(&msg.sender_id, packet);
+                    } else {
+                        info!("Sending movement {:?}", packet);
+                        gamepad_tx.send(packet).await?;
+                    }
+                } else {
+                    info!("Blocked movement from {}", msg.sender_name);
+                }
+            }
+            // Resolved into movements before getting here
+            Alias(_) => {
+                reply_tx
+                    .send(None)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            Hold(buttons) => {
+                let holds = current_game.map_or(&global_holds, |game| &game.holds);
+                let denied = buttons.iter().find(|button| {
+                    !matches!(holds.privilege(**button), Some(privilege) if msg.privilege >= privilege)
+                });
+                if let Some(button) = denied {
+                    reply_tx
+                        .send(Some(format!(
+                            "You can't hold {}",
+                            format!("{:?}", button).to_lowercase()
+                        )))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
+                }
+
                 reply_tx
                     .send(None)
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
 
+                let packet = MovementPacket::hold(buttons, self::Hold::Press, holds.timeout());
                 if !matches!(anarchy_mode, AnarchyType::Restricted)
                     && current_game.is_some_and(|game| game.is_movement_restricted(&packet))
                 {
-                    info!("Packet contains restricted movement {:?}", packet);
+                    info!("Hold contains restricted movement {:?}", packet);
                     continue;
                 }
 
-                if matches!(anarchy_mode, AnarchyType::Streaming) {
-                    info!("Mode is streaming, skipping movement");
+                if matches!(anarchy_mode, AnarchyType::Streaming | AnarchyType::Voting) {
+                    info!("Mode is {}, skipping hold", anarchy_mode.to_str());
+                    continue;
+                }
+
+                if game_loading {
+                    info!("Game is loading, discarding hold {:?}", packet);
                     continue;
                 }
 
@@ -453,11 +2189,75 @@ pub async fn run_commands(
                     || !database::is_blocked(db_conn, &msg.sender_id)
                         .context("Failed to check for blocked user")?
                 {
-                    info!("Sending movement {:?}", packet);
+                    info!("{} is holding {:?}", msg.sender_name, packet.movements);
                     gamepad_tx.send(packet).await?;
                 } else {
-                    info!("Blocked movement from {}", msg.sender_name);
+                    info!("Blocked hold from {}", msg.sender_name);
+                }
+            }
+            Release(_) | ReleaseAll => {
+                let holds = current_game.map_or(&global_holds, |game| &game.holds);
+                let may_hold = |button: &self::Movement| {
+                    holds
+                        .privilege(*button)
+                        .is_some_and(|privilege| msg.privilege >= privilege)
+                };
+                let denied = match msg.command {
+                    Release(ref buttons) => {
+                        let denied = buttons.iter().find(|button| !may_hold(button));
+                        denied.map(|button| {
+                            format!(
+                                "You can't release {}",
+                                format!("{:?}", button).to_lowercase()
+                            )
+                        })
+                    }
+                    // Releasing buttons held by everyone else is up to moderators
+                    _ if msg.privilege < Privilege::Moderator => {
+                        Some("You don't have permission to do that".to_string())
+                    }
+                    _ => None,
+                };
+                if let Some(denied) = denied {
+                    reply_tx
+                        .send(Some(denied))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
+                }
+
+                reply_tx
+                    .send(None)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+
+                if matches!(anarchy_mode, AnarchyType::Streaming | AnarchyType::Voting) {
+                    info!("Mode is {}, skipping release", anarchy_mode.to_str());
+                    continue;
+                }
+
+                if game_loading {
+                    info!(
+                        "Game is loading, discarding release from {}",
+                        msg.sender_name
+                    );
+                    continue;
+                }
+
+                if !matches!(anarchy_mode, AnarchyType::Anarchy)
+                    && database::is_blocked(db_conn, &msg.sender_id)
+                        .context("Failed to check for blocked user")?
+                {
+                    info!("Blocked release from {}", msg.sender_name);
+                    continue;
                 }
+
+                let packet = match msg.command {
+                    Release(buttons) => {
+                        MovementPacket::hold(buttons, self::Hold::Release, Default::default())
+                    }
+                    _ => MovementPacket::release_all(),
+                };
+                info!("{} is releasing {:?}", msg.sender_name, packet.movements);
+                gamepad_tx.send(packet).await?;
             }
             AddOperator(user) => {
                 if msg.privilege >= Privilege::Moderator {
@@ -551,7 +2351,7 @@ pub async fn run_commands(
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 }
             }
-            Game(game) => {
+            Game(_) | RandomGame(_) => {
                 if let AnarchyType::Streaming = anarchy_mode {
                     reply_tx
                         .send(Some(
@@ -561,38 +2361,91 @@ pub async fn run_commands(
                     continue;
                 }
 
-                if msg.privilege >= Privilege::Moderator {
-                    if let Some(game_info) = game_commands.get(&game) {
-                        current_game = Some(game_info);
-                        game_runner_tx
-                            .send(GameRunner::SwitchTo(game_info.command.clone()))
-                            .await?;
-                        reply_tx
-                            .send(None)
-                            .map_err(|_| anyhow!("Failed to reply to command"))?;
-                    } else {
-                        reply_tx
-                            .send(Some(format!(
-                                "No game {} found, see full list with \"tp games\"",
-                                game
-                            )))
-                            .map_err(|_| anyhow!("Failed to reply to command"))?;
-                    }
-                } else {
+                if msg.privilege < Privilege::Moderator {
                     info!(
-                        "{} attempted to switch game to {} with insufficient privilege {:?}",
-                        msg.sender_name, game, msg.privilege
+                        "{} attempted {:?} with insufficient privilege {:?}",
+                        msg.sender_name, msg.command, msg.privilege
                     );
 
                     reply_tx
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
                 }
+
+                let game_info = match &msg.command {
+                    Game(game) => match game_search::find_game(&game_commands, game) {
+                        GameMatch::Exact(game_info) => Ok((game_info, None)),
+                        GameMatch::Close(game_info) => Ok((
+                            game_info,
+                            Some(format!("Switching to {}", game_info.display_name)),
+                        )),
+                        GameMatch::Ambiguous(games) => {
+                            let names: Vec<&str> = games
+                                .iter()
+                                .take(MAX_GAME_SUGGESTIONS)
+                                .map(|game| game.display_name.as_str())
+                                .collect();
+                            Err(format!(
+                                "No game {} found, did you mean {}?",
+                                game,
+                                names.join(", ")
+                            ))
+                        }
+                        GameMatch::NotFound => Err(format!(
+                            "No game {} found, see full list with \"tp games\"",
+                            game
+                        )),
+                    },
+                    RandomGame(tag) => {
+                        let games = match tag {
+                            Some(tag) => game_search::games_tagged(&game_commands, tag),
+                            None => game_commands.values().collect(),
+                        };
+
+                        // Only pick the game that's already running if there's nothing else
+                        let others: Vec<&ConstructedGameInfo> = games
+                            .iter()
+                            .filter(|game| {
+                                current_game.map(|current| &current.name) != Some(&game.name)
+                            })
+                            .copied()
+                            .collect();
+                        let games = if others.is_empty() { games } else { others };
+
+                        match (games.is_empty(), tag) {
+                            (true, Some(tag)) => Err(format!("No games are tagged {}", tag)),
+                            (true, None) => Err("No games are configured".to_owned()),
+                            (false, _) => {
+                                let game_info = games[vote::random_index(games.len())];
+                                Ok((
+                                    game_info,
+                                    Some(format!("Switching to {}", game_info.display_name)),
+                                ))
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+
+                let (game_info, reply) = match game_info {
+                    Ok(game_info) => game_info,
+                    Err(reply) => {
+                        reply_tx
+                            .send(Some(reply))
+                            .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        continue;
+                    }
+                };
+
+                game_change = Some(GameChange::SwitchTo(game_info));
+                reply_tx
+                    .send(reply)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
             Stop => {
                 if msg.privilege >= Privilege::Moderator {
-                    current_game = None;
-                    game_runner_tx.send(GameRunner::Stop).await?;
+                    game_change = Some(GameChange::Stop);
                     reply_tx
                         .send(None)
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
@@ -614,23 +2467,53 @@ pub async fn run_commands(
                     RemoveOperator => "Usage: tp deop <user>",
                     Block => "Usage: tp block <user> [optional: duration]",
                     Unblock => "Usage: tp unblock <user>",
-                    Game => "Usage: tp game <game-name>",
+                    Game => "Usage: tp game <game-name> | random [tag] | search <text>",
+                    GameSearch => "Usage: tp game search <text>",
                     List => "Usage: tp list games | blocked | ops",
                     SetCooldown => "Usage: tp cooldown <duration>",
                     SetAnarchyMode => {
-                        "Usage: tp mode <anarchy | democracy | restricted | streaming>"
+                        "Usage: tp mode <anarchy | democracy | voting | restricted | streaming>"
                     }
                     PlaySfx => "Usage: tp sfx <sound effect>",
+                    VoteSettings => {
+                        "Usage: tp voting window <duration> | threshold <votes> | ties <first | random | skip>"
+                    }
+                    MeterVote => "Usage: tp vote anarchy | democracy",
+                    Meter => "Usage: tp meter lock | unlock",
+                    GameLog => "Usage: tp log [lines, up to 10]",
+                    Nominate => "Usage: tp nominate <game-name>",
+                    VoteGame => "Usage: tp votegame <option number>",
+                    GamePoll => {
+                        "Usage: tp gamepoll start [tag] | cancel | window <duration> | threshold <votes> | options <2 to 10>"
+                    }
+                    Schedule => "Usage: tp schedule [skip | override]",
                 };
 
                 reply_tx
                     .send(Some(diag_msg.to_string()))
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
-            ListGames => {
-                let games: Vec<&str> = game_commands.keys().map(|game| game.as_str()).collect();
+            ListGames(tag) => {
+                let reply = match tag {
+                    Some(tag) => match game_search::games_tagged(&game_commands, &tag) {
+                        games if games.is_empty() => format!("No games are tagged {}", tag),
+                        games => game_list_reply(&games),
+                    },
+                    None => game_list_reply(&game_commands.values().collect::<Vec<_>>()),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            SearchGames(text) => {
+                let reply = match game_search::search_games(&game_commands, &text) {
+                    games if games.is_empty() => format!("No games match {}", text),
+                    games => game_list_reply(&games),
+                };
+
                 reply_tx
-                    .send(Some(games.join(", ")))
+                    .send(Some(reply))
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
             ListOperators => {
@@ -646,20 +2529,46 @@ pub async fn run_commands(
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
             PrintHelp => {
-                let mut available_commands = Vec::new();
-                available_commands
-                    .push("Move with standard controller buttons (up, down, a, b, tl, tr, etc.)");
+                let mut available_commands = vec![
+                    "Move with standard controller buttons (up, down, a, b, tl, tr, etc.)",
+                    "ls/rs <direction> - move the analog sticks",
+                    "lt/rt <percent> - press the analog triggers",
+                    "tp vote anarchy/democracy - move the anarchy meter",
+                    "tp slots - list save slots",
+                    "tp autosave - show the last auto-save",
+                    "tp status - show whether the game is loading",
+                    "tp games [tag] - list games",
+                    "tp game search <text> - find games",
+                    "tp nominate <game> - nominate a game to vote on",
+                    "tp votegame <number> - vote for the next game",
+                    "tp gamepoll - show the game poll",
+                    "tp schedule - show the schedule",
+                ];
                 if msg.privilege >= Privilege::Operator {
                     available_commands.push("tp save/load - save or load state");
                     available_commands.push("tp reset - reset game");
+                    available_commands.push("tp pause - pause or unpause game");
+                    available_commands
+                        .push("tp save/load <slot> - save or load a numbered or named slot");
+                    available_commands.push("tp slot next/prev - change state slot");
                 }
                 if msg.privilege >= Privilege::Moderator {
                     available_commands.push("tp block/unblock - block or unblock a user");
                     available_commands.push("tp op/deop - promote user to operator");
                     available_commands.push("tp list - list games/ops/blocked users");
                     available_commands.push("tp game - switch game");
+                    available_commands.push("tp game random [tag] - switch to a random game");
+                    available_commands.push(
+                        "tp gamepoll start/cancel - start or cancel a vote for the next game",
+                    );
+                    available_commands.push(
+                        "tp schedule skip/override - end the scheduled slot or keep its game",
+                    );
                     available_commands.push("tp mode - set anarchy mode");
                     available_commands.push("tp cooldown - set command cooldown");
+                    available_commands.push("tp voting - configure voting mode");
+                    available_commands.push("tp meter lock/unlock - lock the anarchy meter");
+                    available_commands.push("tp log - show the end of the last game's output");
                 }
                 if msg.privilege >= Privilege::Broadcaster {
                     available_commands.push("tp sfx - play sound effects");
@@ -668,128 +2577,631 @@ pub async fn run_commands(
                     .send(Some(available_commands.join(", ")))
                     .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
-            SaveState => {
+            Emulator(action @ (EmulatorAction::SaveState | EmulatorAction::LoadState))
+                if current_game.is_some() =>
+            {
+                // The selected slot follows the same privileges and records as a numbered one
+                let reply = run_slot_command(
+                    current_game.unwrap(),
+                    &self::SaveSlot::Number(state_slot),
+                    action,
+                    &msg,
+                    &mut state_slot,
+                    db_conn,
+                    &gamepad_tx,
+                )
+                .await?;
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            Emulator(action @ (EmulatorAction::StateSlotPlus | EmulatorAction::StateSlotMinus))
+                if current_game.is_some() && msg.privilege >= Privilege::Operator =>
+            {
+                let game = current_game.unwrap();
+                let slot_num = match action {
+                    EmulatorAction::StateSlotPlus => state_slot.checked_add(1),
+                    _ => state_slot.checked_sub(1),
+                }
+                .filter(|slot_num| *slot_num < game.save_slots.count);
+
+                let reply = match slot_num {
+                    Some(slot_num) => match game
+                        .emulator
+                        .select_slot(state_slot, slot_num, &gamepad_tx)
+                        .await?
+                    {
+                        Ok(()) => {
+                            info!("{} selected slot {}", msg.sender_name, slot_num);
+                            state_slot = slot_num;
+                            format!("Selected state slot {}", slot_num)
+                        }
+                        Err(reply) => reply,
+                    },
+                    None => format!(
+                        "Save slots are numbered 0 to {}",
+                        game.save_slots.count.saturating_sub(1)
+                    ),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            Emulator(action) => {
                 if msg.privilege >= Privilege::Operator {
-                    use crate::command::Movement;
-
-                    // FIXME: Make this more generic
-                    // Right now it's tied to a specific hotkey combo in retroarch
-                    let movements = vec![Movement::Mode, Movement::A];
-                    gamepad_tx
-                        .send(MovementPacket {
-                            movements,
-                            duration: 100,
-                            stagger: 100,
-                            blocking: true,
+                    let control = current_game.map_or(&global_emulator, |game| &game.emulator);
+                    let reply = match control.run(action, &gamepad_tx).await? {
+                        Ok(()) => {
+                            info!("{} ran {}", msg.sender_name, action.to_str());
+                            match action {
+                                EmulatorAction::StateSlotPlus => state_slot += 1,
+                                EmulatorAction::StateSlotMinus => {
+                                    state_slot = state_slot.saturating_sub(1)
+                                }
+                                _ => (),
+                            }
+                            action.done_msg().to_owned()
+                        }
+                        Err(reply) => reply,
+                    };
+
+                    reply_tx
+                        .send(Some(reply))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    info!(
+                        "{} attempted to {} with insufficient privilege {:?}",
+                        msg.sender_name,
+                        action.to_str(),
+                        msg.privilege
+                    );
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            SaveSlot(ref slot) | LoadSlot(ref slot) => {
+                let action = if matches!(msg.command, SaveSlot(_)) {
+                    EmulatorAction::SaveState
+                } else {
+                    EmulatorAction::LoadState
+                };
+
+                let reply = match current_game {
+                    Some(game) => {
+                        run_slot_command(
+                            game,
+                            slot,
+                            action,
+                            &msg,
+                            &mut state_slot,
+                            db_conn,
+                            &gamepad_tx,
+                        )
+                        .await?
+                    }
+                    None => "No game is running".to_owned(),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            ListSlots => {
+                let reply = match current_game {
+                    Some(game) => {
+                        let slots = database::list_save_slots(db_conn, &game.name)?;
+                        if slots.is_empty() {
+                            format!("No saved slots for {}", game.name)
+                        } else {
+                            let slots: Vec<String> = slots
+                                .iter()
+                                .map(|info| {
+                                    let saved_at = info
+                                        .saved_at
+                                        .with_timezone(&chrono::offset::Local)
+                                        .format("%Y-%m-%d %H:%M");
+                                    match info.name {
+                                        Some(ref name) => {
+                                            format!("{} {} ({})", info.slot, name, saved_at)
+                                        }
+                                        None => format!("{} ({})", info.slot, saved_at),
+                                    }
+                                })
+                                .collect();
+                            format!("Save slots for {}: {}", game.name, slots.join(", "))
+                        }
+                    }
+                    None => "No game is running".to_owned(),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            PrintAutosave => {
+                let reply = match current_game {
+                    Some(game) => match game.autosave_interval {
+                        Some(interval) => {
+                            let autosave = database::list_save_slots(db_conn, &game.name)?
+                                .into_iter()
+                                .find(|info| info.name.as_deref() == Some(AUTOSAVE_SLOT_NAME));
+
+                            match autosave {
+                                Some(info) => format!(
+                                    "Last auto-save for {} was at {} in slot {}, saving every {} seconds",
+                                    game.name,
+                                    info.saved_at
+                                        .with_timezone(&chrono::offset::Local)
+                                        .format("%Y-%m-%d %H:%M:%S"),
+                                    info.slot,
+                                    interval.as_secs_f64()
+                                ),
+                                None => format!(
+                                    "No auto-saves yet for {}, saving every {} seconds",
+                                    game.name,
+                                    interval.as_secs_f64()
+                                ),
+                            }
+                        }
+                        None => format!("Auto-save is not enabled for {}", game.name),
+                    },
+                    None => "No game is running".to_owned(),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            PrintGameStatus => {
+                let reply = match current_game {
+                    Some(game) if game_loading => format!("{} is loading", game.name),
+                    Some(game) => format!("{} is ready", game.name),
+                    None => "No game is running".to_owned(),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            GameLog(lines) => {
+                if msg.privilege >= Privilege::Moderator {
+                    let reply =
+                        game_log_reply(config, last_game, current_game.is_some(), lines).await?;
+                    reply_tx
+                        .send(Some(reply))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            PlaySfx(sfx) => {
+                if msg.privilege >= Privilege::Broadcaster {
+                    reply_tx
+                        .send(None)
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    if let Some(ref mut player) = sfx_player_tx {
+                        player
+                            .send(SfxRequest::Named(sfx))
+                            .map_err(|_| anyhow!("Failed to send sfx request"))?;
+                    }
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            SetVoteWindow(window) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(db_conn, CONFIG_KV_VOTE_WINDOW, window.num_milliseconds())?;
+                    vote_window = window;
+                    reply_tx
+                        .send(Some(format!(
+                            "Set vote window to {} seconds",
+                            vote_window.num_milliseconds() as f64 / 1000f64
+                        )))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            SetVoteMinVotes(min_votes) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(db_conn, CONFIG_KV_VOTE_MIN_VOTES, min_votes)?;
+                    vote_min_votes = min_votes;
+                    reply_tx
+                        .send(Some(format!("Votes now need at least {} votes", min_votes)))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            SetVoteTieBreak(tie_break) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(db_conn, CONFIG_KV_VOTE_TIE_BREAK, tie_break.to_str())?;
+                    vote_tie_break = tie_break;
+                    reply_tx
+                        .send(Some(format!(
+                            "Vote ties are now broken by {}",
+                            tie_break.to_str()
+                        )))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                } else {
+                    reply_tx
+                        .send(Some("You don't have permission to do that".to_string()))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                }
+            }
+            PrintVoteSettings => {
+                reply_tx
+                    .send(Some(format!(
+                        "Vote window is {} seconds, votes need at least {} votes, ties are broken by {}",
+                        vote_window.num_milliseconds() as f64 / 1000f64,
+                        vote_min_votes,
+                        vote_tie_break.to_str()
+                    )))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            Nominate(game) => {
+                if let AnarchyType::Streaming = anarchy_mode {
+                    reply_tx
+                        .send(Some(
+                            "Cannot start game in streaming mode, change mode first".to_owned(),
+                        ))
+                        .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
+                }
+
+                let game_info = match game_search::find_game(&game_commands, &game) {
+                    GameMatch::Exact(game_info) | GameMatch::Close(game_info) => game_info,
+                    GameMatch::Ambiguous(games) => {
+                        let names: Vec<&str> = games
+                            .iter()
+                            .take(MAX_GAME_SUGGESTIONS)
+                            .map(|game| game.display_name.as_str())
+                            .collect();
+                        reply_tx
+                            .send(Some(format!(
+                                "No game {} found, did you mean {}?",
+                                game,
+                                names.join(", ")
+                            )))
+                            .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        continue;
+                    }
+                    GameMatch::NotFound => {
+                        reply_tx
+                            .send(Some(format!(
+                                "No game {} found, see full list with \"tp games\"",
+                                game
+                            )))
+                            .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        continue;
+                    }
+                };
+
+                let reply = if current_game.map(|current| &current.name) == Some(&game_info.name) {
+                    format!("{} is already being played", game_info.display_name)
+                } else {
+                    match game_poll {
+                        Some(ref mut poll) => {
+                            match poll
+                                .options
+                                .iter()
+                                .position(|option| option.name == game_info.name)
+                            {
+                                Some(idx) => {
+                                    format!(
+                                        "{} is already option {}",
+                                        game_info.display_name,
+                                        idx + 1
+                                    )
+                                }
+                                None if poll.options.len() >= game_poll_options => format!(
+                                    "The game poll is full, vote with tp votegame <number>: {}",
+                                    poll.format_options()
+                                ),
+                                None => {
+                                    poll.options.push(game_info);
+                                    format!(
+                                        "Nominated {} as option {}",
+                                        game_info.display_name,
+                                        poll.options.len()
+                                    )
+                                }
+                            }
+                        }
+                        None => {
+                            let now = tokio::time::Instant::now();
+                            let wait = game_polls_opened
+                                .get(&msg.sender_id)
+                                .filter(|_| msg.privilege < Privilege::Moderator)
+                                .map(|opened| {
+                                    (*opened + GAME_POLL_OPEN_COOLDOWN).duration_since(now)
+                                })
+                                .filter(|wait| !wait.is_zero());
+                            if let Some(wait) = wait {
+                                reply_tx
+                                    .send(Some(format!(
+                                        "You can open another game poll in {} seconds",
+                                        wait.as_secs().max(1)
+                                    )))
+                                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+                                continue;
+                            }
+
+                            info!(
+                                "{} opened a game poll with {}",
+                                msg.sender_name, game_info.name
+                            );
+                            game_polls_opened.insert(msg.sender_id.clone(), now);
+                            let window = game_poll_window.to_std()?;
+                            game_poll = Some(GamePoll::new(window, vec![game_info]));
+                            format!(
+                                "Game poll open for {} seconds with {} as option 1, nominate more with tp nominate <game> and vote with tp votegame <number>",
+                                window.as_secs_f64(),
+                                game_info.display_name
+                            )
+                        }
+                    }
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            VoteGame(option) => {
+                let reply = match game_poll {
+                    Some(ref mut poll) if option <= poll.options.len() => {
+                        info!("{} voted for game option {}", msg.sender_name, option);
+                        poll.poll.vote(&msg.sender_id, option - 1);
+                        None
+                    }
+                    Some(ref poll) => Some(format!(
+                        "Pick an option from 1 to {}: {}",
+                        poll.options.len(),
+                        poll.format_options()
+                    )),
+                    None => Some(
+                        "No game poll is open, nominate a game with tp nominate <game>".to_owned(),
+                    ),
+                };
+
+                reply_tx
+                    .send(reply)
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            StartGamePoll(tag) => {
+                let reply = if msg.privilege < Privilege::Moderator {
+                    "You don't have permission to do that".to_owned()
+                } else if let AnarchyType::Streaming = anarchy_mode {
+                    "Cannot start game in streaming mode, change mode first".to_owned()
+                } else if game_poll.is_some() {
+                    "A game poll is already open, cancel it with tp gamepoll cancel".to_owned()
+                } else {
+                    let games = match tag {
+                        Some(ref tag) => game_search::games_tagged(&game_commands, tag),
+                        None => game_commands.values().collect(),
+                    };
+                    let games: Vec<&ConstructedGameInfo> = games
+                        .into_iter()
+                        .filter(|game| {
+                            current_game.map(|current| &current.name) != Some(&game.name)
                         })
-                        .await?;
+                        .collect();
+
+                    if games.len() < 2 {
+                        "Not enough games to vote on".to_owned()
+                    } else {
+                        let window = game_poll_window.to_std()?;
+                        let poll = GamePoll::new(window, random_games(games, game_poll_options));
+                        let reply = format!(
+                            "Game poll open for {} seconds, vote with tp votegame <number>: {}",
+                            window.as_secs_f64(),
+                            poll.format_options()
+                        );
+                        info!("{} started a game poll", msg.sender_name);
+                        game_poll = Some(poll);
+                        reply
+                    }
+                };
 
-                    info!("{} saved state", msg.sender_name);
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            CancelGamePoll => {
+                let reply = if msg.privilege < Privilege::Moderator {
+                    "You don't have permission to do that"
+                } else if game_poll.take().is_some() {
+                    info!("{} cancelled the game poll", msg.sender_name);
+                    "Cancelled the game poll"
+                } else {
+                    "No game poll is open"
+                };
+
+                reply_tx
+                    .send(Some(reply.to_owned()))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            SetGamePollWindow(window) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(
+                        db_conn,
+                        CONFIG_KV_GAME_POLL_WINDOW,
+                        window.num_milliseconds(),
+                    )?;
+                    game_poll_window = window;
                     reply_tx
-                        .send(Some("Saved game state".to_string()))
+                        .send(Some(format!(
+                            "Game polls now last {} seconds",
+                            game_poll_window.num_milliseconds() as f64 / 1000f64
+                        )))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 } else {
-                    info!(
-                        "{} attempted to save state with insufficient privilege {:?}",
-                        msg.sender_name, msg.privilege
-                    );
                     reply_tx
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 }
             }
-            LoadState => {
-                if msg.privilege >= Privilege::Operator {
-                    use crate::command::Movement;
-
-                    // FIXME: Make this more generic
-                    // Right now it's tied to a specific hotkey combo in retroarch
-                    let movements = vec![Movement::Mode, Movement::B];
-                    gamepad_tx
-                        .send(MovementPacket {
-                            movements,
-                            duration: 100,
-                            stagger: 100,
-                            blocking: true,
-                        })
-                        .await?;
-
-                    info!("{} loaded state", msg.sender_name);
+            SetGamePollMinVotes(min_votes) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(db_conn, CONFIG_KV_GAME_POLL_MIN_VOTES, min_votes)?;
+                    game_poll_min_votes = min_votes;
                     reply_tx
-                        .send(Some("Loaded game state".to_string()))
+                        .send(Some(format!(
+                            "Game polls now need at least {} votes",
+                            min_votes
+                        )))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 } else {
-                    info!(
-                        "{} attempted to save state with insufficient privilege {:?}",
-                        msg.sender_name, msg.privilege
-                    );
                     reply_tx
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 }
             }
-            Reset => {
-                if msg.privilege >= Privilege::Operator {
-                    use crate::command::Movement;
-
-                    // FIXME: Make this more generic
-                    // Right now it's tied to a specific hotkey combo in retroarch
-                    let movements = vec![Movement::Mode, Movement::X];
-                    gamepad_tx
-                        .send(MovementPacket {
-                            movements,
-                            duration: 100,
-                            stagger: 100,
-                            blocking: true,
-                        })
-                        .await?;
-
-                    info!("{} reset the system", msg.sender_name);
+            SetGamePollOptions(options) => {
+                if msg.privilege >= Privilege::Moderator {
+                    database::set_kv(db_conn, CONFIG_KV_GAME_POLL_OPTIONS, options)?;
+                    game_poll_options = options;
                     reply_tx
-                        .send(Some("Reset current game".to_string()))
+                        .send(Some(format!(
+                            "Game polls now have up to {} options",
+                            options
+                        )))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 } else {
-                    info!(
-                        "{} attempted to save state with insufficient privilege {:?}",
-                        msg.sender_name, msg.privilege
-                    );
                     reply_tx
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
                 }
             }
-            PlaySfx(sfx) => {
-                if msg.privilege >= Privilege::Broadcaster {
-                    reply_tx
-                        .send(None)
-                        .map_err(|_| anyhow!("Failed to reply to command"))?;
-                    if let Some(ref mut player) = sfx_player_tx {
-                        player
-                            .send(SfxRequest::Named(sfx))
-                            .map_err(|_| anyhow!("Failed to send sfx request"))?;
-                    }
-                } else {
+            PrintGamePoll => {
+                let reply = match game_poll {
+                    Some(ref poll) => format!(
+                        "Game poll closes in {} seconds, vote with tp votegame <number>: {}",
+                        poll.poll.remaining().as_secs(),
+                        poll.format_options()
+                    ),
+                    None => format!(
+                        "No game poll is open, polls last {} seconds, need at least {} votes and have up to {} options",
+                        game_poll_window.num_milliseconds() as f64 / 1000f64,
+                        game_poll_min_votes,
+                        game_poll_options
+                    ),
+                };
+
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            PrintSchedule => {
+                reply_tx
+                    .send(Some(print_schedule(schedule)))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
+            }
+            SkipScheduledSlot | OverrideScheduledSlot => {
+                if msg.privilege < Privilege::Moderator {
                     reply_tx
                         .send(Some("You don't have permission to do that".to_string()))
                         .map_err(|_| anyhow!("Failed to reply to command"))?;
+                    continue;
                 }
+
+                let now = chrono::Local::now().naive_local();
+                let run = match schedule_run.as_ref().filter(|run| run.end > now) {
+                    Some(run) => run,
+                    None => {
+                        reply_tx
+                            .send(Some("No scheduled slot is running".to_owned()))
+                            .map_err(|_| anyhow!("Failed to reply to command"))?;
+                        continue;
+                    }
+                };
+
+                let slot = &schedule[run.slot];
+                let reply = if let SkipScheduledSlot = msg.command {
+                    if schedule_stop_pending
+                        && current_game.map(|game| &game.name) == slot.game.as_ref()
+                    {
+                        game_change = Some(GameChange::Stop);
+                    }
+                    info!(
+                        "{} skipped scheduled slot {}",
+                        msg.sender_name,
+                        slot.describe()
+                    );
+                    format!("Skipped {}", slot.describe())
+                } else {
+                    info!(
+                        "{} overrode scheduled slot {}",
+                        msg.sender_name,
+                        slot.describe()
+                    );
+                    format!(
+                        "Overriding {}, nothing will be stopped when it ends at {}",
+                        slot.describe(),
+                        run.end.format("%H:%M")
+                    )
+                };
+
+                schedule_stop_pending = false;
+                database::set_kv(
+                    db_conn,
+                    CONFIG_KV_SCHEDULE_STOP_PENDING,
+                    schedule_stop_pending.to_string(),
+                )?;
+                reply_tx
+                    .send(Some(reply))
+                    .map_err(|_| anyhow!("Failed to reply to command"))?;
             }
             Controls(game_arg) => {
                 let game = match &game_arg {
-                    Some(x) => game_commands.get(x.as_str()),
+                    Some(x) => match game_search::find_game(&game_commands, x) {
+                        GameMatch::Exact(game) | GameMatch::Close(game) => Some(game),
+                        GameMatch::Ambiguous(_) | GameMatch::NotFound => None,
+                    },
                     None => current_game,
                 };
 
                 let controls_text = match game {
-                    Some(game) => match &game.controls_msg {
-                        Some(msg) => format!("{} controls: {}", game.name, msg),
-                        None => format!("{} has no specific controls", game.name),
-                    },
+                    Some(game) => {
+                        let aliases: Vec<String> = game
+                            .aliases
+                            .iter()
+                            .map(|(word, movement)| format!("{} = {}", word, movement))
+                            .collect();
+                        match (&game.controls_msg, aliases.is_empty()) {
+                            (Some(msg), true) => {
+                                format!("{} controls: {}", game.display_name, msg)
+                            }
+                            (Some(msg), false) => format!(
+                                "{} controls: {}, aliases: {}",
+                                game.display_name,
+                                msg,
+                                aliases.join(", ")
+                            ),
+                            (None, false) => {
+                                format!("{} aliases: {}", game.display_name, aliases.join(", "))
+                            }
+                            (None, true) => {
+                                format!("{} has no specific controls", game.display_name)
+                            }
+                        }
+                    }
                     None => {
                         if game_arg.is_none() {
-                            format!("No game is being played currently")
+                            "No game is being played currently".to_owned()
                         } else {
-                            format!("No game of that name found")
+                            "No game of that name found".to_owned()
                         }
                     }
                 };
@@ -806,7 +3218,9 @@ pub async fn run_commands(
 
 #[cfg(test)]
 mod parsing_test {
-    use super::{parse_command, Command, Movement, PartialCommand};
+    use super::{
+        parse_command, Axis, Command, EmulatorAction, Movement, PartialCommand, SaveSlot, AXIS_MIN,
+    };
 
     macro_rules! test_command {
         ($id: ident, $cmd: expr, $result: expr) => {
@@ -821,9 +3235,32 @@ mod parsing_test {
         let movements = Vec::from(movements);
         Some(Command::Movement(super::MovementPacket {
             movements,
+            axes: vec![],
+            duration,
+            stagger: 0,
+            blocking: false,
+            chain: vec![],
+            repeat: None,
+            hold: None,
+            frames: None,
+        }))
+    }
+
+    fn stick_packet(
+        movements: &[Movement],
+        axes: &[(Axis, i32)],
+        duration: u64,
+    ) -> Option<Command> {
+        Some(Command::Movement(super::MovementPacket {
+            movements: Vec::from(movements),
+            axes: Vec::from(axes),
             duration,
             stagger: 0,
             blocking: false,
+            chain: vec![],
+            repeat: None,
+            hold: None,
+            frames: None,
         }))
     }
 
@@ -914,6 +3351,27 @@ mod parsing_test {
         "a 0.6",
         movement_packet(&[Movement::A], 600)
     );
+    test_command!(
+        parse_movement_millis,
+        "a 250ms",
+        movement_packet(&[Movement::A], 250)
+    );
+    test_command!(
+        parse_movement_seconds_suffix,
+        "a 2s",
+        movement_packet(&[Movement::A], 2000)
+    );
+    test_command!(
+        parse_movement_frames,
+        "a 3f",
+        Some(Command::Movement(super::MovementPacket {
+            frames: Some(3),
+            ..repeated_packet(&[Movement::A], 50, None)
+        }))
+    );
+    test_command!(parse_movement_millis_too_long, "a 5001ms", None);
+    test_command!(parse_movement_frames_too_long, "a 301f", None);
+    test_command!(parse_movement_fractional_frames, "a 1.5f", None);
     test_command!(
         parse_movement_multiple_with_time,
         "a b x y lt rt 1",
@@ -945,6 +3403,304 @@ mod parsing_test {
         )
     );
 
+    test_command!(
+        parse_stick_named_direction,
+        "ls up",
+        stick_packet(&[], &[(Axis::X, 128), (Axis::Y, AXIS_MIN)], 100)
+    );
+    test_command!(
+        parse_stick_full_deflection,
+        "ls up 100%",
+        stick_packet(&[], &[(Axis::X, 128), (Axis::Y, AXIS_MIN)], 100)
+    );
+    test_command!(
+        parse_stick_angle_with_duration,
+        "ls 45 0.8",
+        stick_packet(&[], &[(Axis::X, 218), (Axis::Y, 37)], 800)
+    );
+    test_command!(
+        parse_stick_diagonal_with_duration,
+        "ls ne 2",
+        stick_packet(&[], &[(Axis::X, 218), (Axis::Y, 37)], 2000)
+    );
+    test_command!(
+        parse_stick_magnitude,
+        "ls left 50%",
+        stick_packet(&[], &[(Axis::X, 64), (Axis::Y, 128)], 100)
+    );
+    test_command!(
+        parse_stick_with_buttons,
+        "a ls down 1",
+        stick_packet(&[Movement::A], &[(Axis::X, 128), (Axis::Y, 255)], 1000)
+    );
+    test_command!(
+        parse_right_stick,
+        "rs left",
+        stick_packet(&[], &[(Axis::RX, AXIS_MIN), (Axis::RY, 128)], 100)
+    );
+    test_command!(
+        parse_both_sticks,
+        "ls up rs down",
+        stick_packet(
+            &[],
+            &[
+                (Axis::X, 128),
+                (Axis::Y, AXIS_MIN),
+                (Axis::RX, 128),
+                (Axis::RY, 255)
+            ],
+            100
+        )
+    );
+    test_command!(
+        parse_analog_trigger,
+        "rt 50%",
+        stick_packet(&[], &[(Axis::RZ, 128)], 100)
+    );
+    test_command!(
+        parse_analog_trigger_with_duration,
+        "lt 100% 2",
+        stick_packet(&[], &[(Axis::Z, 255)], 2000)
+    );
+    test_command!(
+        parse_digital_trigger_with_duration,
+        "rt 2",
+        movement_packet(&[Movement::TR], 2000)
+    );
+    test_command!(parse_analog_trigger_repeated, "rt 50% tr 20%", None);
+    test_command!(parse_stick_missing_direction, "ls", None);
+    test_command!(parse_stick_invalid_direction, "ls sideways", None);
+    test_command!(parse_stick_invalid_magnitude, "ls up 150%", None);
+    test_command!(parse_stick_repeated, "ls up ls down", None);
+
+    fn repeated_packet(
+        movements: &[Movement],
+        duration: u64,
+        repeat: Option<super::Repeat>,
+    ) -> super::MovementPacket {
+        super::MovementPacket {
+            movements: Vec::from(movements),
+            axes: vec![],
+            duration,
+            stagger: 0,
+            blocking: false,
+            chain: vec![],
+            repeat,
+            hold: None,
+            frames: None,
+        }
+    }
+
+    test_command!(
+        parse_repeat,
+        "A*5",
+        Some(Command::Movement(repeated_packet(
+            &[Movement::A],
+            100,
+            Some(super::Repeat::Times(5))
+        )))
+    );
+    test_command!(
+        parse_mash,
+        "mash a 3",
+        Some(Command::Movement(repeated_packet(
+            &[Movement::A],
+            3000,
+            Some(super::Repeat::For(3000))
+        )))
+    );
+    test_command!(
+        parse_turbo,
+        "turbo a b 0.5",
+        Some(Command::Movement(repeated_packet(
+            &[Movement::A, Movement::B],
+            500,
+            Some(super::Repeat::Turbo(500))
+        )))
+    );
+    test_command!(
+        parse_repeat_spaced_count,
+        "a *5",
+        Some(Command::Movement(repeated_packet(
+            &[Movement::A],
+            100,
+            Some(super::Repeat::Times(5))
+        )))
+    );
+    test_command!(
+        parse_repeat_spaced_star,
+        "a * 5",
+        Some(Command::Movement(repeated_packet(
+            &[Movement::A],
+            100,
+            Some(super::Repeat::Times(5))
+        )))
+    );
+    test_command!(parse_repeat_several_inputs, "a b*5", None);
+    test_command!(parse_repeat_zero, "a*0", None);
+    test_command!(parse_repeat_too_many, "a*101", None);
+    test_command!(parse_repeat_invalid_input, "hello*5", None);
+    test_command!(parse_mash_without_duration, "mash a", None);
+    test_command!(parse_mash_too_long, "mash a 6", None);
+
+    #[test]
+    fn play_time_includes_chains_and_repeats() {
+        let Some(Command::Movement(mut packet)) = parse_command("a*5 > b 2 > c 500ms") else {
+            panic!("did not parse movement");
+        };
+
+        super::limit_repeats(
+            &mut packet,
+            crate::config::RepeatLimits {
+                rate: 10,
+                turbo_rate: 20,
+                max_presses: 50,
+            },
+        );
+        assert_eq!(super::play_time(&packet), 450 + 2000 + 500);
+
+        let Some(Command::Movement(packet)) = parse_command("a 5 > b 5 > c 5") else {
+            panic!("did not parse movement");
+        };
+        assert!(super::play_time(&packet) > super::MAX_PLAY_TIME_MS);
+    }
+
+    #[test]
+    fn turbo_presses_faster_than_mash() {
+        let limits = crate::config::RepeatLimits {
+            rate: 10,
+            turbo_rate: 20,
+            max_presses: 50,
+        };
+        let limited = |input: &str| {
+            let Some(Command::Movement(mut packet)) = parse_command(input) else {
+                panic!("did not parse movement");
+            };
+            super::limit_repeats(&mut packet, limits);
+            (packet.repeat, packet.duration)
+        };
+
+        assert_eq!(limited("mash a 1"), (Some(super::Repeat::Times(10)), 50));
+        assert_eq!(limited("turbo a 1"), (Some(super::Repeat::Times(20)), 25));
+    }
+
+    #[test]
+    fn frames_are_resolved_at_game_fps() {
+        let Some(Command::Movement(mut packet)) = parse_command("mash a 30f > b 1f") else {
+            panic!("did not parse movement");
+        };
+
+        super::resolve_frames(&mut packet, 30);
+        assert_eq!(packet.duration, 1000);
+        assert_eq!(packet.repeat, Some(super::Repeat::For(1000)));
+        assert_eq!(packet.frames, None);
+        assert_eq!(packet.chain[0].duration, 33);
+    }
+
+    fn chain_packet(steps: &[(&[Movement], u64)]) -> Option<Command> {
+        let mut packets: Vec<super::MovementPacket> = steps
+            .iter()
+            .map(
+                |(movements, duration)| match movement_packet(movements, *duration) {
+                    Some(Command::Movement(packet)) => packet,
+                    _ => unreachable!(),
+                },
+            )
+            .collect();
+        let mut packet = packets.remove(0);
+        packet.chain = packets;
+        Some(Command::Movement(packet))
+    }
+
+    test_command!(
+        parse_chain_arrows,
+        "down>right>a",
+        chain_packet(&[
+            (&[Movement::Down], 100),
+            (&[Movement::Right], 100),
+            (&[Movement::A], 100)
+        ])
+    );
+    test_command!(
+        parse_chain_commas,
+        "down, right, a 0.2",
+        chain_packet(&[
+            (&[Movement::Down], 100),
+            (&[Movement::Right], 100),
+            (&[Movement::A], 200)
+        ])
+    );
+    test_command!(
+        parse_chain_simultaneous_steps,
+        "Down B 1 > A",
+        chain_packet(&[
+            (&[Movement::Down, Movement::B], 1000),
+            (&[Movement::A], 100)
+        ])
+    );
+    test_command!(
+        parse_chain_with_repeats,
+        "a*2 > b",
+        Some(Command::Movement(super::MovementPacket {
+            repeat: Some(super::Repeat::Times(2)),
+            chain: vec![repeated_packet(&[Movement::B], 100, None)],
+            ..repeated_packet(&[Movement::A], 100, None)
+        }))
+    );
+    test_command!(parse_chain_empty_step, "down>>a", None);
+    test_command!(parse_chain_trailing_separator, "down, right,", None);
+    test_command!(parse_chain_invalid_step, "down>hello", None);
+    test_command!(parse_chain_too_long, "a>a>a>a>a>a>a>a>a>a>a", None);
+
+    test_command!(parse_hold, "hold b", Some(Command::Hold(vec![Movement::B])));
+    test_command!(
+        parse_hold_multiple,
+        "hold b tr b",
+        Some(Command::Hold(vec![Movement::B, Movement::TR]))
+    );
+    test_command!(
+        parse_release,
+        "release b",
+        Some(Command::Release(vec![Movement::B]))
+    );
+    test_command!(parse_release_all, "release all", Some(Command::ReleaseAll));
+    test_command!(parse_hold_nothing, "hold", None);
+    test_command!(parse_hold_duration, "hold b 2", None);
+
+    #[test]
+    fn aliases_are_parsed_when_nothing_else_matches() {
+        let words = ["jump".to_owned(), "a".to_owned()].into_iter().collect();
+        let parse = |input| super::parse_command_with_aliases(input, &words);
+
+        assert_eq!(parse("Jump>b"), Some(Command::Alias("jump>b".to_owned())));
+        assert_eq!(parse("a"), parse_command("a"));
+        assert_eq!(parse("hello there"), None);
+        assert_eq!(parse("I want to jump lol"), None);
+        assert_eq!(parse("jump 2"), Some(Command::Alias("jump 2".to_owned())));
+        assert_eq!(
+            parse("mash jump 1"),
+            Some(Command::Alias("mash jump 1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn aliases_resolve_to_movements() {
+        let aliases = [
+            ("jump".to_owned(), "b".to_owned()),
+            ("hadouken".to_owned(), "down>right>a".to_owned()),
+            ("menu".to_owned(), "tp controls".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let resolve = |input| super::resolve_aliases(input, &aliases);
+
+        assert_eq!(resolve("jump 2"), parse_command("b 2"));
+        assert_eq!(resolve("jump a"), parse_command("b a"));
+        assert_eq!(resolve("hadouken, jump"), parse_command("down>right>a>b"));
+        assert_eq!(resolve("menu"), None);
+        assert_eq!(resolve("punch"), None);
+    }
+
     test_command!(
         parse_block,
         "tp block user",
@@ -994,11 +3750,20 @@ mod parsing_test {
         Some(Command::ListOperators)
     );
     test_command!(parse_list_op_op, "tp list op", Some(Command::ListOperators));
-    test_command!(parse_list_games, "tp list games", Some(Command::ListGames));
+    test_command!(
+        parse_list_games,
+        "tp list games",
+        Some(Command::ListGames(None))
+    );
     test_command!(
         parse_list_games_direct,
         "tp games",
-        Some(Command::ListGames)
+        Some(Command::ListGames(None))
+    );
+    test_command!(
+        parse_list_games_tagged,
+        "tp games Retro Platformer",
+        Some(Command::ListGames(Some("retro platformer".to_owned())))
     );
 
     test_command!(parse_invalid, "asdf", None);
@@ -1057,9 +3822,64 @@ mod parsing_test {
         Some(Command::Partial(PartialCommand::SetCooldown))
     );
 
-    test_command!(parse_save, "tp save", Some(Command::SaveState));
-    test_command!(parse_load, "tp load", Some(Command::LoadState));
-    test_command!(parse_reset, "tp reset", Some(Command::Reset));
+    test_command!(
+        parse_save,
+        "tp save",
+        Some(Command::Emulator(EmulatorAction::SaveState))
+    );
+    test_command!(
+        parse_load,
+        "tp load",
+        Some(Command::Emulator(EmulatorAction::LoadState))
+    );
+    test_command!(
+        parse_reset,
+        "tp reset",
+        Some(Command::Emulator(EmulatorAction::Reset))
+    );
+    test_command!(
+        parse_save_numbered_slot,
+        "tp save 3",
+        Some(Command::SaveSlot(SaveSlot::Number(3)))
+    );
+    test_command!(
+        parse_load_named_slot,
+        "tp load Boss-Fight",
+        Some(Command::LoadSlot(SaveSlot::Name("boss-fight".to_owned())))
+    );
+    test_command!(parse_list_slots, "tp slots", Some(Command::ListSlots));
+    test_command!(parse_autosave, "tp autosave", Some(Command::PrintAutosave));
+    test_command!(
+        parse_game_status,
+        "tp status",
+        Some(Command::PrintGameStatus)
+    );
+    test_command!(parse_game_log, "tp log", Some(Command::GameLog(5)));
+    test_command!(
+        parse_game_log_lines,
+        "tp log 10",
+        Some(Command::GameLog(10))
+    );
+    test_command!(
+        parse_game_log_too_many_lines,
+        "tp log 50",
+        Some(Command::Partial(PartialCommand::GameLog))
+    );
+    test_command!(
+        parse_pause,
+        "tp pause",
+        Some(Command::Emulator(EmulatorAction::Pause))
+    );
+    test_command!(
+        parse_next_slot,
+        "tp slot next",
+        Some(Command::Emulator(EmulatorAction::StateSlotPlus))
+    );
+    test_command!(
+        parse_previous_slot,
+        "tp slot -",
+        Some(Command::Emulator(EmulatorAction::StateSlotMinus))
+    );
 
     test_command!(parse_print_mode, "tp mode", Some(Command::PrintAnarchyMode));
     test_command!(
@@ -1098,6 +3918,63 @@ mod parsing_test {
         ))
     );
 
+    test_command!(
+        parse_voting,
+        "tp mode voting",
+        Some(Command::SetAnarchyMode(crate::command::AnarchyType::Voting))
+    );
+    test_command!(
+        parse_vote_window,
+        "tp voting window 5s",
+        Some(Command::SetVoteWindow(chrono::Duration::seconds(5)))
+    );
+    test_command!(
+        parse_vote_threshold,
+        "tp voting threshold 3",
+        Some(Command::SetVoteMinVotes(3))
+    );
+    test_command!(
+        parse_vote_ties,
+        "tp voting ties random",
+        Some(Command::SetVoteTieBreak(crate::vote::TieBreak::Random))
+    );
+    test_command!(
+        parse_vote_settings,
+        "tp voting",
+        Some(Command::PrintVoteSettings)
+    );
+    test_command!(
+        parse_malformed_vote_window,
+        "tp voting window 0s",
+        Some(Command::Partial(PartialCommand::VoteSettings))
+    );
+
+    test_command!(
+        parse_meter_vote_anarchy,
+        "tp vote anarchy",
+        Some(Command::MeterVote(crate::meter::MeterVote::Anarchy))
+    );
+    test_command!(
+        parse_meter_vote_democracy,
+        "tp vote democracy",
+        Some(Command::MeterVote(crate::meter::MeterVote::Democracy))
+    );
+    test_command!(
+        parse_partial_meter_vote,
+        "tp vote",
+        Some(Command::Partial(PartialCommand::MeterVote))
+    );
+    test_command!(
+        parse_meter_lock,
+        "tp meter lock",
+        Some(Command::LockMeter(true))
+    );
+    test_command!(
+        parse_meter_unlock,
+        "tp meter unlock",
+        Some(Command::LockMeter(false))
+    );
+
     test_command!(
         parse_sfx,
         "tp sfx sfx_name",
@@ -1124,6 +4001,84 @@ mod parsing_test {
         "tp game game with spaces",
         Some(Command::Game("game with spaces".to_string()))
     );
+    test_command!(
+        parse_game_search,
+        "tp game search Mario Kart",
+        Some(Command::SearchGames("mario kart".to_owned()))
+    );
+    test_command!(
+        parse_game_search_empty,
+        "tp game search",
+        Some(Command::Partial(PartialCommand::GameSearch))
+    );
+    test_command!(
+        parse_random_game,
+        "tp game random",
+        Some(Command::RandomGame(None))
+    );
+    test_command!(
+        parse_random_game_tagged,
+        "tp game random snes",
+        Some(Command::RandomGame(Some("snes".to_owned())))
+    );
+    test_command!(
+        parse_nominate,
+        "tp nominate Super Metroid",
+        Some(Command::Nominate("super metroid".to_owned()))
+    );
+    test_command!(
+        parse_nominate_empty,
+        "tp nominate",
+        Some(Command::Partial(PartialCommand::Nominate))
+    );
+    test_command!(parse_vote_game, "tp votegame 2", Some(Command::VoteGame(2)));
+    test_command!(
+        parse_vote_game_zero,
+        "tp votegame 0",
+        Some(Command::Partial(PartialCommand::VoteGame))
+    );
+    test_command!(parse_game_poll, "tp gamepoll", Some(Command::PrintGamePoll));
+    test_command!(
+        parse_game_poll_start_tagged,
+        "tp gamepoll start snes",
+        Some(Command::StartGamePoll(Some("snes".to_owned())))
+    );
+    test_command!(
+        parse_game_poll_cancel,
+        "tp gamepoll cancel",
+        Some(Command::CancelGamePoll)
+    );
+    test_command!(
+        parse_game_poll_window,
+        "tp gamepoll window 2m",
+        Some(Command::SetGamePollWindow(chrono::Duration::seconds(120)))
+    );
+    test_command!(
+        parse_game_poll_options_out_of_range,
+        "tp gamepoll options 11",
+        Some(Command::Partial(PartialCommand::GamePoll))
+    );
+    test_command!(parse_schedule, "tp schedule", Some(Command::PrintSchedule));
+    test_command!(
+        parse_schedule_skip,
+        "tp schedule skip",
+        Some(Command::SkipScheduledSlot)
+    );
+    test_command!(
+        parse_schedule_override,
+        "tp schedule override",
+        Some(Command::OverrideScheduledSlot)
+    );
+    test_command!(
+        parse_schedule_unknown,
+        "tp schedule next",
+        Some(Command::Partial(PartialCommand::Schedule))
+    );
+    test_command!(
+        parse_switch_random,
+        "tp switch random",
+        Some(Command::Game("random".to_owned()))
+    );
     test_command!(parse_stop, "tp stop", Some(Command::Stop));
 
     test_command!(parse_controls, "tp controls", Some(Command::Controls(None)));
@@ -1,11 +1,22 @@
 use anyhow::anyhow;
 use serde::{de::Error, Deserialize, Deserializer};
 use std::{
-    collections::{BTreeMap, HashSet},
-    path::PathBuf,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
 };
 
-use crate::command::{parse_movement_token, Movement, MovementPacket};
+use crate::{
+    command::{
+        parse_movement_token, parse_stick_token, parse_trigger_token, Axis, Movement,
+        MovementPacket, Privilege,
+    },
+    emulator::{EmulatorAction, EmulatorConfig, EmulatorControl},
+    game_log::GameLogConfig,
+    game_runner::{
+        GameHooks, ReadyCheck, ResourceLimits, RestartPolicy, RestartSettings, DEFAULT_STOP_TIMEOUT,
+    },
+    schedule::ScheduleSlot,
+};
 
 fn deserialize_u64_map<'d, D, T>(deserializer: D) -> Result<BTreeMap<u64, T>, D::Error>
 where
@@ -24,23 +35,128 @@ where
     u64_key_map
 }
 
+fn deserialize_option_nonzero_duration<'d, D>(
+    deserializer: D,
+) -> Result<Option<std::time::Duration>, D::Error>
+where
+    D: Deserializer<'d>,
+{
+    match duration_str::deserialize_option_duration(deserializer)? {
+        Some(duration) if duration.is_zero() => Err(D::Error::custom("duration must not be zero")),
+        duration => Ok(duration),
+    }
+}
+
+fn deserialize_movement_map<'d, D>(
+    deserializer: D,
+) -> Result<Option<HashMap<Movement, Privilege>>, D::Error>
+where
+    D: Deserializer<'d>,
+{
+    let orig_map: BTreeMap<String, Privilege> = BTreeMap::deserialize(deserializer)?;
+    orig_map
+        .into_iter()
+        .map(|(k, v)| {
+            parse_movement_token(&k.to_lowercase())
+                .map(|k| (k, v))
+                .ok_or_else(|| D::Error::custom(format!("invalid button {}", k)))
+        })
+        .collect::<Result<HashMap<Movement, Privilege>, D::Error>>()
+        .map(Some)
+}
+
+fn lowercase_aliases<'a>(
+    aliases: impl Iterator<Item = (&'a String, &'a String)>,
+) -> BTreeMap<String, String> {
+    aliases
+        .map(|(word, movement)| (word.to_lowercase(), movement.to_lowercase()))
+        .collect()
+}
+
 pub type GameName = String;
 
-#[derive(Clone, Deserialize)]
+const DEFAULT_READY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Presses per second for repeats and mash
+const DEFAULT_MASH_RATE: u64 = 10;
+/// Presses per second for turbo, only lowered by a game's `max_mash_rate`
+const TURBO_RATE: u64 = 20;
+const DEFAULT_MAX_REPEATS: u64 = 50;
+
+/// Frame rate used for durations in frames like `a 3f` when a game doesn't set `fps`
+pub const DEFAULT_FPS: u64 = 60;
+
+const DEFAULT_HOLD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How fast and how many times inputs can be pressed by repeats, turbo and mash
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RepeatLimits {
+    /// Presses per second
+    pub rate: u64,
+    /// Presses per second for turbo
+    pub turbo_rate: u64,
+    pub max_presses: u64,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadingInput {
+    #[default]
+    Discard,
+    /// Queue movements and play them once the game is ready
+    Hold,
+}
+
+#[derive(Clone, Default, Deserialize)]
 pub struct GameCommandString(pub String);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct GameCommand {
+    /// Name of the game this command launches, used for its log files
+    pub name: GameName,
     pub command: String,
     pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    pub hooks: GameHooks,
+    pub ready: Option<ReadyCheck>,
+    /// The game is treated as ready once this passes, even if its check never does
+    pub ready_timeout: std::time::Duration,
+    pub limits: ResourceLimits,
+    pub restart: RestartSettings,
+    pub stop_timeout: std::time::Duration,
 }
 
 #[derive(Clone)]
 pub struct ConstructedGameInfo {
     pub name: String,
+    /// Shown in game lists, the name by default
+    pub display_name: String,
+    pub tags: Vec<String>,
     pub command: GameCommand,
     pub restricted_inputs: HashSet<Movement>,
+    pub restricted_axes: HashSet<Axis>,
     pub controls_msg: Option<String>,
+    pub emulator: EmulatorControl,
+    pub save_slots: SaveSlotConfig,
+    pub autosave_interval: Option<std::time::Duration>,
+    pub while_loading: LoadingInput,
+    pub repeat_limits: RepeatLimits,
+    pub holds: HoldConfig,
+    pub fps: u64,
+    /// Words chat can use in movements, with the global aliases this game doesn't replace
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// Button combos that trigger emulator actions, resolved into packets
+#[derive(Clone, Debug, Default)]
+pub struct Hotkeys {
+    pub save_state: Option<MovementPacket>,
+    pub load_state: Option<MovementPacket>,
+    pub reset: Option<MovementPacket>,
+    pub pause: Option<MovementPacket>,
+    pub state_slot_plus: Option<MovementPacket>,
+    pub state_slot_minus: Option<MovementPacket>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -60,11 +176,168 @@ pub struct TwitchConfig {
     pub auth: TwitchAuth,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Default, Deserialize)]
 pub struct GameInfo {
     pub command: GameCommandString,
+    /// Shown in game lists instead of the name, `tp game` accepts either
+    pub display_name: Option<String>,
+    /// Used to filter `tp games` and pick random games
+    pub tags: Option<Vec<String>>,
+    /// Extra arguments passed as-is after those in `command`
+    pub args: Option<Vec<String>>,
+    /// Environment variables set for the game, on top of our own
+    pub env: Option<BTreeMap<String, String>>,
+    /// Working directory for the game, ours by default
+    pub cwd: Option<PathBuf>,
+    /// Run before each launch, the game isn't started if this fails
+    pub pre_launch: Option<GameCommandString>,
+    /// Run after each launch, once the game has been started
+    pub post_launch: Option<GameCommandString>,
+    /// Run each time the game exits or is stopped
+    pub post_exit: Option<GameCommandString>,
+    /// How long hooks can run before they're killed, 30 seconds by default
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub hook_timeout: Option<std::time::Duration>,
+    /// How to tell the game has loaded, chat input is held back until then
+    pub ready: Option<ReadyCheck>,
+    /// Gives up on the readiness check after this, 2 minutes by default
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub ready_timeout: Option<std::time::Duration>,
+    /// What happens to movements sent while the game is loading, discarded by default
+    pub while_loading: Option<LoadingInput>,
+    /// Limits applied to the game's process before it starts
+    pub limits: Option<ResourceLimits>,
     pub restricted_inputs: Option<Vec<String>>,
     pub controls: Option<String>,
+    pub hotkeys: Option<HotkeysConfig>,
+    pub emulator: Option<EmulatorConfig>,
+    pub save_slots: Option<SaveSlotConfig>,
+    /// Saves to the `autosave` slot on this interval and before the game is stopped
+    #[serde(default, deserialize_with = "deserialize_option_nonzero_duration")]
+    pub autosave_interval: Option<std::time::Duration>,
+    /// Whether to restart the game when it exits on its own, never by default
+    pub restart: Option<RestartPolicy>,
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart, doubled for each following attempt
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub restart_backoff: Option<std::time::Duration>,
+    /// Overrides the global `stop_timeout` for this game
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub stop_timeout: Option<std::time::Duration>,
+    /// Most presses a single repeat, turbo or mash can make, 50 by default
+    pub max_repeats: Option<u64>,
+    /// Caps the global `mash_rate` and the turbo rate for this game
+    pub max_mash_rate: Option<u64>,
+    pub holds: Option<HoldConfig>,
+    /// Frame rate durations in frames are converted with, 60 by default
+    pub fps: Option<u64>,
+    /// Words that stand for movements in this game, e.g. `jump = "b"` or `hadouken = "down>right>a"`
+    pub aliases: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HotkeyConfig {
+    /// Buttons pressed in order, e.g. `["mode", "a"]`
+    pub buttons: Vec<String>,
+    /// Delay between each button press
+    pub stagger_ms: Option<u64>,
+    /// How long the buttons are held once all are pressed
+    pub hold_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HotkeysConfig {
+    pub save_state: Option<HotkeyConfig>,
+    pub load_state: Option<HotkeyConfig>,
+    pub reset: Option<HotkeyConfig>,
+    pub pause: Option<HotkeyConfig>,
+    pub state_slot_plus: Option<HotkeyConfig>,
+    pub state_slot_minus: Option<HotkeyConfig>,
+}
+
+const DEFAULT_SAVE_SLOT_COUNT: u64 = 10;
+
+fn default_save_slot_count() -> u64 {
+    DEFAULT_SAVE_SLOT_COUNT
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SaveSlotConfig {
+    /// Slots are numbered from 0 up to but not including this
+    #[serde(default = "default_save_slot_count")]
+    pub count: u64,
+    /// Privilege needed to overwrite an existing save in a slot, operators by default
+    #[serde(default, deserialize_with = "deserialize_u64_map")]
+    pub overwrite_privilege: BTreeMap<u64, Privilege>,
+    /// Privilege needed to load a slot, operators by default
+    #[serde(default, deserialize_with = "deserialize_u64_map")]
+    pub load_privilege: BTreeMap<u64, Privilege>,
+}
+
+impl Default for SaveSlotConfig {
+    fn default() -> Self {
+        SaveSlotConfig {
+            count: DEFAULT_SAVE_SLOT_COUNT,
+            overwrite_privilege: BTreeMap::new(),
+            load_privilege: BTreeMap::new(),
+        }
+    }
+}
+
+impl SaveSlotConfig {
+    pub fn overwrite_privilege(&self, slot: u64) -> Privilege {
+        self.overwrite_privilege
+            .get(&slot)
+            .copied()
+            .unwrap_or(Privilege::Operator)
+    }
+
+    pub fn load_privilege(&self, slot: u64) -> Privilege {
+        self.load_privilege
+            .get(&slot)
+            .copied()
+            .unwrap_or(Privilege::Operator)
+    }
+}
+
+/// Which buttons can be held with `hold` and for how long
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HoldConfig {
+    /// Held buttons are released after this, 30 seconds by default
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub timeout: Option<std::time::Duration>,
+    /// Privilege needed to hold each button, only listed buttons can be held if set
+    #[serde(default, deserialize_with = "deserialize_movement_map")]
+    pub privilege: Option<HashMap<Movement, Privilege>>,
+}
+
+impl HoldConfig {
+    pub fn timeout(&self) -> std::time::Duration {
+        self.timeout.unwrap_or(DEFAULT_HOLD_TIMEOUT)
+    }
+
+    /// Privilege needed to hold a button, `None` if it can't be held. Anyone can hold any button by default
+    pub fn privilege(&self, movement: Movement) -> Option<Privilege> {
+        match self.privilege {
+            Some(ref privilege) => privilege.get(&movement).copied(),
+            None => Some(Privilege::Standard),
+        }
+    }
 }
 
 #[derive(Clone, Deserialize)]
@@ -76,11 +349,63 @@ pub struct SoundEffectConfig {
     pub sub_events: BTreeMap<u64, String>,
 }
 
+/// A directory of ROMs, each of which becomes a game launched with the command for its extension
+#[derive(Clone, Deserialize)]
+pub struct RomDirConfig {
+    /// Relative paths are relative to the config file
+    pub path: PathBuf,
+    /// Also scan subdirectories
+    #[serde(default)]
+    pub recursive: bool,
+    /// Tags given to every game in the directory
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Command templates by file extension, `{path}` is replaced with the ROM's path
+    pub commands: BTreeMap<String, String>,
+}
+
+/// Per-ROM overrides read from a `<stem>.toml` file next to the ROM
+#[derive(Default, Deserialize)]
+struct RomSidecar {
+    display_name: Option<String>,
+    /// Added to the directory's tags
+    tags: Option<Vec<String>>,
+    restricted_inputs: Option<Vec<String>>,
+    controls: Option<String>,
+}
+
 #[derive(Clone, Deserialize)]
 pub struct Config {
     pub twitch: TwitchConfig,
     pub sound_effects: Option<SoundEffectConfig>,
     pub games: Option<BTreeMap<GameName, GameInfo>>,
+    /// Directories scanned for games on top of those in `games`
+    pub rom_dirs: Option<Vec<RomDirConfig>>,
+    pub hotkeys: Option<HotkeysConfig>,
+    pub emulator: Option<EmulatorConfig>,
+    pub save_slots: Option<SaveSlotConfig>,
+    pub holds: Option<HoldConfig>,
+    /// Loads the `autosave` slot this long after resuming the previous game at startup, games
+    /// with a readiness check load it once they're ready instead
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub resume_autosave_delay: Option<std::time::Duration>,
+    /// Game output is logged unless this is disabled, see `read_config` for its default
+    pub game_logs: Option<GameLogConfig>,
+    /// How long games have to exit after SIGTERM before they're killed
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub stop_timeout: Option<std::time::Duration>,
+    /// Games and modes switched to at set times, in local time
+    pub schedule: Option<Vec<ScheduleSlot>>,
+    /// Presses per second for repeats and mash, 10 by default
+    pub mash_rate: Option<u64>,
+    /// Words that stand for movements in every game, games can replace them
+    pub aliases: Option<BTreeMap<String, String>>,
 }
 
 fn cfg_path() -> anyhow::Result<PathBuf> {
@@ -107,17 +432,295 @@ fn cfg_path() -> anyhow::Result<PathBuf> {
 pub async fn read_config() -> anyhow::Result<(Config, PathBuf)> {
     let cfg_path = cfg_path()?;
     let cfg = tokio::fs::read_to_string(&cfg_path).await?;
-    let cfg: Config = toml::from_str(&cfg)?;
+    let mut cfg: Config = toml::from_str(&cfg)?;
+
+    let game_logs = cfg.game_logs.get_or_insert_with(GameLogConfig::default);
+    if game_logs.dir.is_relative() {
+        if let Some(cfg_dir) = cfg_path.parent() {
+            game_logs.dir = cfg_dir.join(&game_logs.dir);
+        }
+    }
+
+    for rom_dir in cfg.rom_dirs.iter_mut().flatten() {
+        if rom_dir.path.is_relative() {
+            if let Some(cfg_dir) = cfg_path.parent() {
+                rom_dir.path = cfg_dir.join(&rom_dir.path);
+            }
+        }
+    }
+
     Ok((cfg, cfg_path))
 }
 
+/// Splits a command line into words like a POSIX shell, without any expansions
+pub fn split_shell_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\\' => match chars.next() {
+                // An escaped newline continues the line
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_owned()),
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_owned()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Only these keep their special meaning inside double quotes
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('\\' | '"' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_owned()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_owned()),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(word) = word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+/// Quotes a word so `split_shell_words` gives it back unchanged
+fn quote_shell_word(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
 impl GameCommandString {
-    pub fn to_command(&self) -> GameCommand {
-        let mut args = self.0.split(' ').map(|s| s.to_owned());
+    fn to_words(&self, name: &str) -> Vec<String> {
+        split_shell_words(&self.0)
+            .unwrap_or_else(|e| panic!("invalid command for game {}: {}", name, e))
+    }
+
+    pub fn to_command(&self, name: &str) -> GameCommand {
+        let mut args = self.to_words(name).into_iter();
         let command = args.next().expect("game command should include a command");
         let args: Vec<String> = args.collect();
 
-        GameCommand { command, args }
+        GameCommand {
+            name: name.to_owned(),
+            command,
+            args,
+            env: BTreeMap::new(),
+            cwd: None,
+            hooks: GameHooks::default(),
+            ready: None,
+            ready_timeout: DEFAULT_READY_TIMEOUT,
+            limits: ResourceLimits::default(),
+            restart: RestartSettings::default(),
+            stop_timeout: DEFAULT_STOP_TIMEOUT,
+        }
+    }
+}
+
+const DEFAULT_HOTKEY_STAGGER_MS: u64 = 100;
+const DEFAULT_HOTKEY_HOLD_MS: u64 = 100;
+
+impl HotkeyConfig {
+    fn to_packet(&self) -> MovementPacket {
+        let movements = self
+            .buttons
+            .iter()
+            .map(|b| {
+                let b = b.to_lowercase();
+                // Chat can't press mode, but it's needed for most emulator hotkeys
+                match b.as_str() {
+                    "mode" => Movement::Mode,
+                    _ => parse_movement_token(&b)
+                        .unwrap_or_else(|| panic!("invalid hotkey button {}", b)),
+                }
+            })
+            .collect();
+
+        MovementPacket {
+            movements,
+            axes: vec![],
+            duration: self.hold_ms.unwrap_or(DEFAULT_HOTKEY_HOLD_MS),
+            stagger: self.stagger_ms.unwrap_or(DEFAULT_HOTKEY_STAGGER_MS),
+            blocking: true,
+            chain: vec![],
+            repeat: None,
+            hold: None,
+            frames: None,
+        }
+    }
+}
+
+impl Hotkeys {
+    /// Game specific hotkeys take priority over global hotkeys for each action
+    fn resolve(game: Option<&HotkeysConfig>, global: Option<&HotkeysConfig>) -> Self {
+        let pick = |f: fn(&HotkeysConfig) -> &Option<HotkeyConfig>| {
+            game.and_then(|hk| f(hk).as_ref())
+                .or_else(|| global.and_then(|hk| f(hk).as_ref()))
+                .map(HotkeyConfig::to_packet)
+        };
+
+        Hotkeys {
+            save_state: pick(|hk| &hk.save_state),
+            load_state: pick(|hk| &hk.load_state),
+            reset: pick(|hk| &hk.reset),
+            pause: pick(|hk| &hk.pause),
+            state_slot_plus: pick(|hk| &hk.state_slot_plus),
+            state_slot_minus: pick(|hk| &hk.state_slot_minus),
+        }
+    }
+
+    pub fn get(&self, action: EmulatorAction) -> Option<&MovementPacket> {
+        match action {
+            EmulatorAction::SaveState => self.save_state.as_ref(),
+            EmulatorAction::LoadState => self.load_state.as_ref(),
+            EmulatorAction::Reset => self.reset.as_ref(),
+            EmulatorAction::Pause => self.pause.as_ref(),
+            EmulatorAction::StateSlotPlus => self.state_slot_plus.as_ref(),
+            EmulatorAction::StateSlotMinus => self.state_slot_minus.as_ref(),
+        }
+    }
+}
+
+/// Whether an entry in `restricted_inputs` names a button, stick or trigger
+fn is_restricted_input(input: &str) -> bool {
+    let input = input.to_lowercase();
+    parse_stick_token(&input).is_some()
+        || parse_trigger_token(&input).is_some()
+        || parse_movement_token(&input).is_some()
+}
+
+impl RomDirConfig {
+    /// Files in the directory, sorted by path
+    fn scan(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.path.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::warn!("Failed to read ROM directory {:?}: {:?}", dir, e);
+                    continue;
+                }
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                // Symlinked directories aren't followed, so a link back up the tree can't loop
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    if self.recursive {
+                        dirs.push(path);
+                    }
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files
+    }
+
+    fn template(&self, rom: &Path) -> Option<&String> {
+        let ext = rom.extension()?.to_str()?;
+        self.commands
+            .iter()
+            .find(|(pattern, _)| {
+                pattern
+                    .trim_start_matches('*')
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(ext)
+            })
+            .map(|(_, template)| template)
+    }
+
+    /// Builds a game from a ROM, or `None` if no command matches its extension
+    fn game_info(&self, rom: &Path) -> Option<(GameName, GameInfo)> {
+        let template = self.template(rom)?;
+        let (name, path) = match (rom.file_stem().and_then(|s| s.to_str()), rom.to_str()) {
+            (Some(name), Some(path)) => (name, path),
+            _ => {
+                tracing::warn!("Skipping ROM with a non UTF-8 path: {:?}", rom);
+                return None;
+            }
+        };
+
+        // Substituting after splitting keeps the path a single argument
+        let command: Vec<String> = match split_shell_words(template) {
+            Ok(words) if !words.is_empty() => words
+                .iter()
+                .map(|word| quote_shell_word(&word.replace("{path}", path)))
+                .collect(),
+            Ok(_) => {
+                tracing::warn!("Skipping ROM {:?}, its command template is empty", rom);
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping ROM {:?}, invalid command template {}: {}",
+                    rom,
+                    template,
+                    e
+                );
+                return None;
+            }
+        };
+
+        let sidecar_path = rom.with_extension("toml");
+        let sidecar: RomSidecar = match std::fs::read_to_string(&sidecar_path) {
+            Ok(sidecar) => match toml::from_str(&sidecar) {
+                Ok(sidecar) => sidecar,
+                Err(e) => {
+                    tracing::warn!("Skipping ROM {:?}, invalid overrides: {}", rom, e);
+                    return None;
+                }
+            },
+            Err(_) => RomSidecar::default(),
+        };
+
+        Some((
+            name.to_owned(),
+            GameInfo {
+                command: GameCommandString(command.join(" ")),
+                display_name: sidecar.display_name,
+                tags: Some(
+                    self.tags
+                        .iter()
+                        .chain(sidecar.tags.iter().flatten())
+                        .cloned()
+                        .collect(),
+                ),
+                restricted_inputs: sidecar.restricted_inputs,
+                controls: sidecar.controls,
+                ..Default::default()
+            },
+        ))
     }
 }
 
@@ -129,40 +732,418 @@ impl ConstructedGameInfo {
             }
         }
 
-        false
+        for (axis, _) in packet.axes.iter() {
+            if self.restricted_axes.contains(axis) {
+                return true;
+            }
+        }
+
+        packet
+            .chain
+            .iter()
+            .any(|packet| self.is_movement_restricted(packet))
     }
 }
 
 impl Config {
+    /// Repeat limits used when no game is running
+    pub fn global_repeat_limits(&self) -> RepeatLimits {
+        RepeatLimits {
+            rate: self.mash_rate.unwrap_or(DEFAULT_MASH_RATE).max(1),
+            turbo_rate: TURBO_RATE,
+            max_presses: DEFAULT_MAX_REPEATS,
+        }
+    }
+
+    /// Aliases used when no game is running
+    pub fn global_aliases(&self) -> BTreeMap<String, String> {
+        lowercase_aliases(self.aliases.iter().flatten())
+    }
+
+    /// Every word used as an alias by any game, messages containing one are resolved by the command runner
+    pub fn alias_words(&self) -> HashSet<String> {
+        self.aliases
+            .iter()
+            .chain(
+                self.games
+                    .iter()
+                    .flat_map(|games| games.values().filter_map(|gi| gi.aliases.as_ref())),
+            )
+            .flat_map(|aliases| aliases.keys())
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+
+    /// Emulator control used when no game is running
+    pub fn global_emulator_control(&self) -> EmulatorControl {
+        EmulatorControl::new(
+            self.emulator.as_ref(),
+            Hotkeys::resolve(None, self.hotkeys.as_ref()),
+        )
+    }
+
+    pub fn game_logs(&self) -> Option<&GameLogConfig> {
+        self.game_logs.as_ref().filter(|logs| logs.enabled)
+    }
+
+    /// Games found in `rom_dirs`, the first ROM found wins if several have the same name
+    fn discover_games(&self) -> BTreeMap<GameName, GameInfo> {
+        let mut games = BTreeMap::new();
+        for rom_dir in self.rom_dirs.iter().flatten() {
+            for rom in rom_dir.scan() {
+                let (name, gi) = match rom_dir.game_info(&rom) {
+                    Some(game) => game,
+                    None => continue,
+                };
+
+                if games.contains_key(&name) {
+                    tracing::warn!("Skipping ROM {:?}, {} was already found", rom, name);
+                    continue;
+                }
+                games.insert(name, gi);
+            }
+        }
+
+        games
+    }
+
     pub fn game_command_list(&self) -> BTreeMap<GameName, ConstructedGameInfo> {
-        self.games
-            .as_ref()
-            .map(|games| {
-                games
+        // Games configured by hand replace discovered ROMs with the same name
+        let mut games = self.discover_games();
+        if let Some(ref configured) = self.games {
+            games.extend(
+                configured
                     .iter()
-                    .map(|(name, gi)| {
-                        let mut ri = HashSet::new();
-                        if let Some(ref restricted_inputs) = gi.restricted_inputs {
-                            for m in restricted_inputs.iter() {
-                                let m = m.to_lowercase();
-                                let m =
-                                    parse_movement_token(&m).expect("invalid restricted movement");
-                                ri.insert(m);
-                            }
-                        }
-
-                        (
-                            name.to_owned(),
-                            ConstructedGameInfo {
-                                name: name.to_owned(),
-                                command: gi.command.to_command(),
-                                restricted_inputs: ri,
-                                controls_msg: gi.controls.clone(),
-                            },
-                        )
-                    })
-                    .collect()
+                    .map(|(name, gi)| (name.clone(), gi.clone())),
+            );
+        }
+
+        let global_repeat_limits = self.global_repeat_limits();
+        games
+            .iter()
+            .filter(|(name, gi)| {
+                match gi
+                    .restricted_inputs
+                    .iter()
+                    .flatten()
+                    .find(|input| !is_restricted_input(input))
+                {
+                    Some(input) => {
+                        tracing::warn!(
+                            "Skipping game {}, invalid restricted input {}",
+                            name,
+                            input
+                        );
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .map(|(name, gi)| {
+                let mut ri = HashSet::new();
+                let mut ra = HashSet::new();
+                for m in gi.restricted_inputs.iter().flatten() {
+                    let m = m.to_lowercase();
+                    if let Some((x, y)) = parse_stick_token(&m) {
+                        ra.insert(x);
+                        ra.insert(y);
+                    }
+
+                    // Restricting a trigger restricts both its button and analog axis
+                    if let Some(axis) = parse_trigger_token(&m) {
+                        ra.insert(axis);
+                    }
+
+                    if let Some(movement) = parse_movement_token(&m) {
+                        ri.insert(movement);
+                    }
+                }
+
+                let mut command = gi.command.to_command(name);
+                let default_restart = RestartSettings::default();
+                command.restart = RestartSettings {
+                    policy: gi.restart.unwrap_or(default_restart.policy),
+                    max_retries: gi.max_restarts.unwrap_or(default_restart.max_retries),
+                    backoff: gi.restart_backoff.unwrap_or(default_restart.backoff),
+                };
+                command.args.extend(gi.args.iter().flatten().cloned());
+                command.env = gi.env.clone().unwrap_or_default();
+                command.cwd = gi.cwd.clone();
+                let hook = |hook: &Option<GameCommandString>| {
+                    hook.as_ref().map(|hook| hook.to_words(name))
+                };
+                command.hooks = GameHooks {
+                    pre_launch: hook(&gi.pre_launch),
+                    post_launch: hook(&gi.post_launch),
+                    post_exit: hook(&gi.post_exit),
+                    timeout: gi.hook_timeout.unwrap_or(GameHooks::default().timeout),
+                };
+                command.limits = gi.limits.unwrap_or_default();
+                command.ready = gi.ready.clone();
+                command.ready_timeout = gi.ready_timeout.unwrap_or(DEFAULT_READY_TIMEOUT);
+                command.stop_timeout = gi
+                    .stop_timeout
+                    .or(self.stop_timeout)
+                    .unwrap_or(DEFAULT_STOP_TIMEOUT);
+
+                (
+                    name.to_owned(),
+                    ConstructedGameInfo {
+                        name: name.to_owned(),
+                        display_name: gi.display_name.clone().unwrap_or_else(|| name.to_owned()),
+                        tags: gi.tags.clone().unwrap_or_default(),
+                        command,
+                        restricted_inputs: ri,
+                        restricted_axes: ra,
+                        controls_msg: gi.controls.clone(),
+                        emulator: EmulatorControl::new(
+                            gi.emulator.as_ref().or(self.emulator.as_ref()),
+                            Hotkeys::resolve(gi.hotkeys.as_ref(), self.hotkeys.as_ref()),
+                        ),
+                        save_slots: gi
+                            .save_slots
+                            .as_ref()
+                            .or(self.save_slots.as_ref())
+                            .cloned()
+                            .unwrap_or_default(),
+                        autosave_interval: gi.autosave_interval,
+                        while_loading: gi.while_loading.unwrap_or_default(),
+                        repeat_limits: RepeatLimits {
+                            rate: gi.max_mash_rate.map_or(global_repeat_limits.rate, |rate| {
+                                rate.clamp(1, global_repeat_limits.rate)
+                            }),
+                            turbo_rate: gi
+                                .max_mash_rate
+                                .map_or(TURBO_RATE, |rate| rate.clamp(1, TURBO_RATE)),
+                            max_presses: gi.max_repeats.unwrap_or(DEFAULT_MAX_REPEATS).max(1),
+                        },
+                        holds: gi
+                            .holds
+                            .as_ref()
+                            .or(self.holds.as_ref())
+                            .cloned()
+                            .unwrap_or_default(),
+                        fps: gi.fps.unwrap_or(DEFAULT_FPS).max(1),
+                        aliases: lowercase_aliases(
+                            self.aliases.iter().chain(gi.aliases.iter()).flatten(),
+                        ),
+                    },
+                )
             })
-            .unwrap_or_default()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod shell_words_test {
+    use super::split_shell_words;
+
+    fn split(line: &str) -> Vec<String> {
+        split_shell_words(line).unwrap()
+    }
+
+    #[test]
+    fn words_are_split_on_whitespace() {
+        assert_eq!(
+            split("retroarch  -L\tcore.so "),
+            vec!["retroarch", "-L", "core.so"]
+        );
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn quotes_group_words() {
+        assert_eq!(
+            split("retroarch -L core.so '/roms/Super Game (USA).sfc'"),
+            vec!["retroarch", "-L", "core.so", "/roms/Super Game (USA).sfc"]
+        );
+        assert_eq!(
+            split(r#"game --title="Two Words" '' """#),
+            vec!["game", "--title=Two Words", "", ""]
+        );
+        assert_eq!(split(r#"'it'"'"'s'"#), vec!["it's"]);
+    }
+
+    #[test]
+    fn backslashes_escape() {
+        assert_eq!(
+            split(r"/roms/Super\ Game.sfc"),
+            vec!["/roms/Super Game.sfc"]
+        );
+        assert_eq!(split(r"'C:\roms'"), vec![r"C:\roms"]);
+        assert_eq!(
+            split(r#""say \"hi\" \$HOME \n""#),
+            vec![r#"say "hi" $HOME \n"#]
+        );
+    }
+
+    #[test]
+    fn unterminated_quotes_are_errors() {
+        assert!(split_shell_words("game 'rom").is_err());
+        assert!(split_shell_words("game \"rom").is_err());
+        assert!(split_shell_words("game rom\\").is_err());
+    }
+}
+
+#[cfg(test)]
+mod rom_dir_test {
+    use std::path::PathBuf;
+
+    use crate::command::Movement;
+
+    use super::Config;
+
+    fn rom_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("twitch-gamepad-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("hacks")).unwrap();
+        dir
+    }
+
+    fn config(extra: &str) -> Config {
+        let cfg = format!(
+            "{}\n[twitch]\nchannel_name = \"\"\n[twitch.auth]\ntype = \"Anonymous\"\n",
+            extra
+        );
+        toml::from_str(&cfg).unwrap()
+    }
+
+    #[test]
+    fn roms_are_discovered_by_extension() {
+        let dir = rom_dir("roms");
+        for file in [
+            "Super Game (USA).sfc",
+            "It's a Game.SMC",
+            "notes.txt",
+            "hacks/Hack.sfc",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let cfg = config(&format!(
+            "[[rom_dirs]]\npath = {:?}\n[rom_dirs.commands]\n\"*.sfc\" = \"retroarch -L 'snes 9x.so' {{path}}\"\nsmc = \"snes --rom={{path}}\"\n",
+            dir
+        ));
+        let games = cfg.game_command_list();
+        assert_eq!(
+            games.keys().collect::<Vec<_>>(),
+            vec!["It's a Game", "Super Game (USA)"]
+        );
+
+        let game = &games["Super Game (USA)"].command;
+        assert_eq!(game.name, "Super Game (USA)");
+        assert_eq!(game.command, "retroarch");
+        assert_eq!(
+            game.args,
+            vec![
+                "-L".to_owned(),
+                "snes 9x.so".to_owned(),
+                dir.join("Super Game (USA).sfc")
+                    .to_str()
+                    .unwrap()
+                    .to_owned()
+            ]
+        );
+
+        let game = &games["It's a Game"].command;
+        assert_eq!(
+            game.args,
+            vec![format!(
+                "--rom={}",
+                dir.join("It's a Game.SMC").to_str().unwrap()
+            )]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sidecars_and_configured_games_override_roms() {
+        let dir = rom_dir("rom-overrides");
+        for file in ["Game.sfc", "Other.sfc", "hacks/Hack.sfc"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(
+            dir.join("Game.toml"),
+            "display_name = \"The Game\"\ntags = [\"platformer\"]\ncontrols = \"B to jump\"\nrestricted_inputs = [\"start\"]\n",
+        )
+        .unwrap();
+
+        let cfg = config(&format!(
+            "[[rom_dirs]]\npath = {:?}\nrecursive = true\ntags = [\"snes\"]\n[rom_dirs.commands]\nsfc = \"snes {{path}}\"\n[games.Other]\ncommand = \"other-game\"\n",
+            dir
+        ));
+        let games = cfg.game_command_list();
+        assert_eq!(games.len(), 3);
+
+        let game = &games["Game"];
+        assert_eq!(game.controls_msg.as_deref(), Some("B to jump"));
+        assert!(game.restricted_inputs.contains(&Movement::Start));
+        assert_eq!(game.display_name, "The Game");
+        assert_eq!(game.tags, vec!["snes", "platformer"]);
+        assert!(games["Hack"].restricted_inputs.is_empty());
+        assert_eq!(games["Hack"].display_name, "Hack");
+        assert_eq!(games["Other"].command.command, "other-game");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn games_with_invalid_restricted_inputs_are_skipped() {
+        let cfg = config(
+            "[games.Good]\ncommand = \"good\"\nrestricted_inputs = [\"start\", \"ls\", \"LT\"]\n[games.Bad]\ncommand = \"bad\"\nrestricted_inputs = [\"start\", \"turbo\"]\n",
+        );
+        let games = cfg.game_command_list();
+        assert_eq!(games.keys().collect::<Vec<_>>(), vec!["Good"]);
+        assert!(games["Good"].restricted_inputs.contains(&Movement::Start));
+        assert_eq!(games["Good"].restricted_axes.len(), 3);
+    }
+
+    #[test]
+    fn roms_with_broken_sidecars_are_skipped() {
+        let dir = rom_dir("rom-broken-sidecars");
+        for file in ["Game.sfc", "Broken.sfc", "Restricted.sfc"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(dir.join("Broken.toml"), "display_name = \"unterminated").unwrap();
+        std::fs::write(
+            dir.join("Restricted.toml"),
+            "restricted_inputs = [\"turbo\"]\n",
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("hacks/loop")).unwrap();
+
+        let cfg = config(&format!(
+            "[[rom_dirs]]\npath = {:?}\nrecursive = true\n[rom_dirs.commands]\nsfc = \"snes {{path}}\"\nsmc = \"snes 'unterminated {{path}}\"\n",
+            dir
+        ));
+        std::fs::write(dir.join("Quoted.smc"), "").unwrap();
+
+        let games = cfg.game_command_list();
+        assert_eq!(games.keys().collect::<Vec<_>>(), vec!["Game"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod game_info_test {
+    use super::GameInfo;
+
+    #[test]
+    fn zero_autosave_interval_is_rejected() {
+        let parse = |interval: &str| {
+            toml::from_str::<GameInfo>(&format!(
+                "command = \"game\"\nautosave_interval = \"{}\"",
+                interval
+            ))
+        };
+
+        assert!(parse("0s").is_err());
+        assert_eq!(
+            parse("10m").unwrap().autosave_interval,
+            Some(std::time::Duration::from_secs(600))
+        );
     }
 }
@@ -7,6 +7,7 @@ pub fn clear_db(conn: &Connection) -> anyhow::Result<()> {
     conn.execute("delete from operators", ())?;
     conn.execute("delete from blocked_users", ())?;
     conn.execute("delete from last_command_time", ())?;
+    conn.execute("delete from save_slots", ())?;
     Ok(())
 }
 
@@ -46,6 +47,19 @@ fn init_db(conn: &Connection) -> rusqlite::Result<()> {
         (),
     )?;
 
+    conn.execute(
+        "create table if not exists save_slots (
+             id integer primary key,
+             game text not null,
+             slot integer not null,
+             name text,
+             saved_at text not null,
+             saved_by text not null,
+             unique(game, slot)
+         )",
+        (),
+    )?;
+
     conn.execute(
         "create table if not exists config_kv (
              id integer primary key,
@@ -282,3 +296,51 @@ pub fn list_op_users(conn: &Connection) -> rusqlite::Result<Vec<String>> {
     let users: rusqlite::Result<Vec<String>> = stmt.query_map((), |row| row.get(0))?.collect();
     users
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveSlotInfo {
+    pub slot: u64,
+    pub name: Option<String>,
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+    pub saved_by: String,
+}
+
+pub fn record_save_slot(
+    conn: &Connection,
+    game: &str,
+    slot: u64,
+    name: Option<&str>,
+    saved_by: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "insert or replace into save_slots(game, slot, name, saved_at, saved_by) values (?1, ?2, ?3, ?4, ?5)",
+        params![game, slot, name, chrono::Utc::now(), saved_by],
+    )?;
+    Ok(())
+}
+
+pub fn find_save_slot(conn: &Connection, game: &str, name: &str) -> rusqlite::Result<Option<u64>> {
+    conn.query_row(
+        "select slot from save_slots where game=?1 and name=?2",
+        params![game, name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn list_save_slots(conn: &Connection, game: &str) -> rusqlite::Result<Vec<SaveSlotInfo>> {
+    let mut stmt = conn.prepare(
+        "select slot, name, saved_at, saved_by from save_slots where game=?1 order by slot",
+    )?;
+    let slots: rusqlite::Result<Vec<SaveSlotInfo>> = stmt
+        .query_map(params![game], |row| {
+            Ok(SaveSlotInfo {
+                slot: row.get(0)?,
+                name: row.get(1)?,
+                saved_at: row.get(2)?,
+                saved_by: row.get(3)?,
+            })
+        })?
+        .collect();
+    slots
+}
@@ -0,0 +1,177 @@
+use std::net::SocketAddr;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use tokio::{net::UdpSocket, sync::mpsc::Sender};
+
+use crate::{command::MovementPacket, config::Hotkeys};
+
+pub const DEFAULT_RETROARCH_ADDRESS: &str = "127.0.0.1:55355";
+
+/// UDP won't report that nobody is listening unless we wait for the refusal
+const REFUSAL_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(50);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmulatorAction {
+    SaveState,
+    LoadState,
+    Reset,
+    Pause,
+    StateSlotPlus,
+    StateSlotMinus,
+}
+
+impl EmulatorAction {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::SaveState => "save state",
+            Self::LoadState => "load state",
+            Self::Reset => "reset",
+            Self::Pause => "pause",
+            Self::StateSlotPlus => "next state slot",
+            Self::StateSlotMinus => "previous state slot",
+        }
+    }
+
+    pub const fn retroarch_command(self) -> &'static str {
+        match self {
+            Self::SaveState => "SAVE_STATE",
+            Self::LoadState => "LOAD_STATE",
+            Self::Reset => "RESET",
+            Self::Pause => "PAUSE_TOGGLE",
+            Self::StateSlotPlus => "STATE_SLOT_PLUS",
+            Self::StateSlotMinus => "STATE_SLOT_MINUS",
+        }
+    }
+
+    /// Reply sent to chat once the action has been sent to the emulator
+    pub const fn done_msg(self) -> &'static str {
+        match self {
+            Self::SaveState => "Saved game state",
+            Self::LoadState => "Loaded game state",
+            Self::Reset => "Reset current game",
+            Self::Pause => "Toggled pause",
+            Self::StateSlotPlus => "Selected next state slot",
+            Self::StateSlotMinus => "Selected previous state slot",
+        }
+    }
+
+    pub const fn unsupported_msg(self) -> &'static str {
+        match self {
+            Self::SaveState => "Saving state is not supported for this game",
+            Self::LoadState => "Loading state is not supported for this game",
+            Self::Reset => "Resetting is not supported for this game",
+            Self::Pause => "Pausing is not supported for this game",
+            Self::StateSlotPlus | Self::StateSlotMinus => {
+                "Changing state slots is not supported for this game"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum EmulatorConfig {
+    /// Press the configured hotkey combos on the virtual gamepad
+    Hotkeys,
+    /// Send RetroArch network commands, requires `network_cmd_enable` in retroarch.cfg
+    RetroArch { address: Option<SocketAddr> },
+}
+
+/// How emulator actions are delivered for a game
+#[derive(Clone, Debug)]
+pub enum EmulatorControl {
+    Hotkeys(Box<Hotkeys>),
+    RetroArch(SocketAddr),
+}
+
+impl EmulatorControl {
+    pub fn new(config: Option<&EmulatorConfig>, hotkeys: Hotkeys) -> Self {
+        match config {
+            None | Some(EmulatorConfig::Hotkeys) => EmulatorControl::Hotkeys(Box::new(hotkeys)),
+            Some(EmulatorConfig::RetroArch { address }) => EmulatorControl::RetroArch(
+                address.unwrap_or_else(|| DEFAULT_RETROARCH_ADDRESS.parse().unwrap()),
+            ),
+        }
+    }
+
+    /// Runs an action on the emulator, the inner error is a reply for chat explaining
+    /// why the action didn't run
+    pub async fn run(
+        &self,
+        action: EmulatorAction,
+        gamepad_tx: &Sender<MovementPacket>,
+    ) -> anyhow::Result<Result<(), String>> {
+        match self {
+            EmulatorControl::Hotkeys(hotkeys) => match hotkeys.get(action) {
+                Some(packet) => {
+                    gamepad_tx.send(packet.clone()).await?;
+                    Ok(Ok(()))
+                }
+                None => Ok(Err(action.unsupported_msg().to_owned())),
+            },
+            EmulatorControl::RetroArch(address) => {
+                match send_retroarch_command(*address, action).await {
+                    Ok(()) => Ok(Ok(())),
+                    Err(e) => {
+                        tracing::warn!("Failed to {}: {:?}", action.to_str(), e);
+                        Ok(Err(format!("Failed to {}: {}", action.to_str(), e)))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Steps the emulator's state slot from `from` to `to`
+    pub async fn select_slot(
+        &self,
+        from: u64,
+        to: u64,
+        gamepad_tx: &Sender<MovementPacket>,
+    ) -> anyhow::Result<Result<(), String>> {
+        let (action, steps) = if to >= from {
+            (EmulatorAction::StateSlotPlus, to - from)
+        } else {
+            (EmulatorAction::StateSlotMinus, from - to)
+        };
+
+        for _ in 0..steps {
+            if let Err(reply) = self.run(action, gamepad_tx).await? {
+                return Ok(Err(reply));
+            }
+        }
+
+        Ok(Ok(()))
+    }
+}
+
+pub async fn send_retroarch_command(
+    address: SocketAddr,
+    action: EmulatorAction,
+) -> anyhow::Result<()> {
+    let bind_address = if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+
+    let send = async {
+        let socket = UdpSocket::bind(bind_address).await?;
+        socket.connect(address).await?;
+        socket.send(action.retroarch_command().as_bytes()).await?;
+
+        // RetroArch doesn't reply to these commands, so a refusal only shows up as a
+        // socket error shortly after sending
+        let mut buf = [0u8; 64];
+        match tokio::time::timeout(REFUSAL_TIMEOUT, socket.recv(&mut buf)).await {
+            Ok(Err(e)) => Err(e),
+            _ => match socket.take_error()? {
+                Some(e) => Err(e),
+                None => Ok(()),
+            },
+        }
+    };
+
+    send.await
+        .map_err(|e| anyhow!("RetroArch at {} did not accept the command: {}", address, e))
+}
@@ -0,0 +1,364 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::Child,
+};
+use tracing::warn;
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_dir() -> PathBuf {
+    PathBuf::from("game_logs")
+}
+
+fn default_max_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    3
+}
+
+fn default_max_sessions() -> usize {
+    5
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GameLogConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Relative paths are relative to the config file
+    #[serde(default = "default_dir")]
+    pub dir: PathBuf,
+    /// A session's log is rotated once it grows past this size
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Log files kept per session, including the one being written
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Sessions kept per game, older sessions are deleted when a game starts
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+}
+
+impl Default for GameLogConfig {
+    fn default() -> Self {
+        GameLogConfig {
+            enabled: default_enabled(),
+            dir: default_dir(),
+            max_bytes: default_max_bytes(),
+            max_files: default_max_files(),
+            max_sessions: default_max_sessions(),
+        }
+    }
+}
+
+impl GameLogConfig {
+    fn game_dir(&self, game: &str) -> PathBuf {
+        let game: String = game
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        self.dir.join(game)
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{}", n));
+    PathBuf::from(path)
+}
+
+/// Session logs of a game, oldest first. Rotated files aren't included.
+async fn list_sessions(game_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut sessions = Vec::new();
+    let mut entries = match tokio::fs::read_dir(game_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(sessions),
+        Err(e) => return Err(e),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "log") {
+            sessions.push(path);
+        }
+    }
+
+    // Session names are timestamps, so they sort in the order they were started
+    sessions.sort();
+    Ok(sessions)
+}
+
+pub struct SessionLog {
+    path: PathBuf,
+    file: tokio::fs::File,
+    written: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl SessionLog {
+    /// Creates the log file for a new session of a game, pruning old sessions
+    pub async fn start(cfg: &GameLogConfig, game: &str) -> std::io::Result<Self> {
+        let game_dir = cfg.game_dir(game);
+        tokio::fs::create_dir_all(&game_dir).await?;
+
+        // Make room for the new session
+        let sessions = list_sessions(&game_dir).await?;
+        let keep = cfg.max_sessions.saturating_sub(1);
+        for old in sessions.iter().take(sessions.len().saturating_sub(keep)) {
+            tokio::fs::remove_file(old).await?;
+            for n in 1..cfg.max_files.max(1) {
+                let _ = tokio::fs::remove_file(rotated_path(old, n)).await;
+            }
+        }
+
+        let name = chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+        let path = game_dir.join(format!("{}.log", name));
+        let file = tokio::fs::File::create(&path).await?;
+
+        Ok(SessionLog {
+            path,
+            file,
+            written: 0,
+            max_bytes: cfg.max_bytes,
+            max_files: cfg.max_files.max(1),
+        })
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush().await?;
+        if self.max_files > 1 {
+            for n in (1..self.max_files - 1).rev() {
+                let from = rotated_path(&self.path, n);
+                if tokio::fs::try_exists(&from).await? {
+                    tokio::fs::rename(&from, rotated_path(&self.path, n + 1)).await?;
+                }
+            }
+            tokio::fs::rename(&self.path, rotated_path(&self.path, 1)).await?;
+        }
+
+        self.file = tokio::fs::File::create(&self.path).await?;
+        self.written = 0;
+        Ok(())
+    }
+
+    async fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate().await?;
+        }
+
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+}
+
+async fn forward_lines<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+) {
+    loop {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Writes the output of a child spawned with piped stdout and stderr into a session log,
+/// also sending each line to `line_tx` if given
+pub fn capture(
+    child: &mut Child,
+    mut log: Option<SessionLog>,
+    line_tx: Option<tokio::sync::mpsc::UnboundedSender<String>>,
+) {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    if let Some(stdout) = child.stdout.take() {
+        tokio::task::spawn(forward_lines(BufReader::new(stdout), tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::task::spawn(forward_lines(BufReader::new(stderr), tx));
+    }
+
+    tokio::task::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            if let Some(ref mut session) = log {
+                if let Err(e) = session.write_line(&line).await {
+                    warn!("Failed to write game log {:?}: {:?}", session.path, e);
+                    log = None;
+                }
+            }
+
+            if let Some(ref line_tx) = line_tx {
+                let line = String::from_utf8_lossy(&line).trim_end().to_owned();
+                let _ = line_tx.send(line);
+            }
+        }
+    });
+}
+
+/// The last lines of a game's previous session, skipping the newest session if the game is
+/// still running and writing to it
+pub async fn previous_session_tail(
+    cfg: &GameLogConfig,
+    game: &str,
+    running: bool,
+    lines: usize,
+) -> std::io::Result<Option<Vec<String>>> {
+    let mut sessions = list_sessions(&cfg.game_dir(game)).await?;
+    if running {
+        sessions.pop();
+    }
+
+    let session = match sessions.pop() {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+
+    // Read back through rotated files until there are enough lines
+    let mut tail: Vec<String> = Vec::new();
+    for n in 0..cfg.max_files.max(1) {
+        let path = match n {
+            0 => session.clone(),
+            n => rotated_path(&session, n),
+        };
+
+        let contents = match tokio::fs::read(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        };
+
+        let mut file_lines: Vec<String> = String::from_utf8_lossy(&contents)
+            .lines()
+            .map(|line| line.to_owned())
+            .collect();
+        file_lines.append(&mut tail);
+        tail = file_lines;
+
+        if tail.len() >= lines {
+            break;
+        }
+    }
+
+    let skip = tail.len().saturating_sub(lines);
+    Ok(Some(tail.split_off(skip)))
+}
+
+#[cfg(test)]
+mod game_log_test {
+    use std::path::PathBuf;
+
+    use super::{previous_session_tail, GameLogConfig, SessionLog};
+
+    fn test_config(name: &str) -> GameLogConfig {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("twitch-gamepad-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        GameLogConfig {
+            dir,
+            max_bytes: 16,
+            max_files: 2,
+            max_sessions: 2,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn sessions_are_rotated_by_size() {
+        let cfg = test_config("rotation");
+        let mut log = SessionLog::start(&cfg, "Game 1").await.unwrap();
+        for line in ["line 1\n", "line 2\n", "line 3\n", "line 4\n", "line 5\n"] {
+            log.write_line(line.as_bytes()).await.unwrap();
+        }
+
+        let files = std::fs::read_dir(cfg.dir.join("Game_1")).unwrap().count();
+        assert_eq!(files, 2);
+
+        // Older lines were rotated out entirely
+        let tail = previous_session_tail(&cfg, "Game 1", false, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tail, vec!["line 3", "line 4", "line 5"]);
+
+        let tail = previous_session_tail(&cfg, "Game 1", false, 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tail, vec!["line 5"]);
+
+        std::fs::remove_dir_all(&cfg.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn running_session_is_skipped() {
+        let cfg = test_config("sessions");
+        assert_eq!(
+            previous_session_tail(&cfg, "game", false, 5).await.unwrap(),
+            None
+        );
+
+        for session in ["first", "second", "third"] {
+            let mut log = SessionLog::start(&cfg, "game").await.unwrap();
+            log.write_line(format!("{}\n", session).as_bytes())
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        let tail = previous_session_tail(&cfg, "game", true, 5).await.unwrap();
+        assert_eq!(tail, Some(vec!["second".to_owned()]));
+
+        // Only the newest sessions are kept
+        let sessions = std::fs::read_dir(cfg.dir.join("game")).unwrap().count();
+        assert_eq!(sessions, 2);
+
+        std::fs::remove_dir_all(&cfg.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn child_output_is_captured() {
+        let cfg = test_config("capture");
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "echo out; echo err >&2"])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let log = SessionLog::start(&cfg, "game").await.unwrap();
+        super::capture(&mut child, Some(log), None);
+        child.wait().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let mut tail = previous_session_tail(&cfg, "game", false, 5)
+            .await
+            .unwrap()
+            .unwrap();
+        tail.sort();
+        assert_eq!(tail, vec!["err", "out"]);
+
+        std::fs::remove_dir_all(&cfg.dir).unwrap();
+    }
+}
@@ -1,22 +1,252 @@
-use crate::config::{GameCommand, SoundEffectConfig};
-use nix::sys::signal::{kill, Signal};
+use crate::config::{GameCommand, GameName, SoundEffectConfig};
+use crate::game_log::{self, GameLogConfig, SessionLog};
+use nix::libc;
+use nix::sys::resource::{setrlimit, Resource};
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use serde::{de::Error, Deserialize, Deserializer};
 use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tracing::{info, warn};
 
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
 use std::sync::Arc;
 
+/// A game that stays up this long has its restart attempts reset
+const RESTART_RESET_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long a game has to exit after SIGTERM before its process group is killed
+pub const DEFAULT_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often to check whether a process group is gone while waiting for it
+const GROUP_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum GameRunner {
-    SwitchTo(GameCommand),
+    SwitchTo(Box<GameCommand>),
     Stop,
+    /// Holds off stopping the current game for this long, giving the emulator time to finish a save
+    DelayStop(std::time::Duration),
 }
 
-async fn wait_on_child(child: &mut Option<Child>) -> anyhow::Result<()> {
-    if let Some(child) = child {
-        child.wait().await?;
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RestartSettings {
+    pub policy: RestartPolicy,
+    pub max_retries: u32,
+    /// Delay before the first restart, doubled for each following attempt
+    pub backoff: std::time::Duration,
+}
+
+impl Default for RestartSettings {
+    fn default() -> Self {
+        RestartSettings {
+            policy: RestartPolicy::Never,
+            max_retries: 3,
+            backoff: std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+impl RestartSettings {
+    fn should_restart(&self, status: &ExitStatus) -> bool {
+        match self.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// Commands run around each launch of a game, split into program and arguments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameHooks {
+    pub pre_launch: Option<Vec<String>>,
+    pub post_launch: Option<Vec<String>>,
+    pub post_exit: Option<Vec<String>>,
+    /// Hooks still running after this are killed and count as failed
+    pub timeout: std::time::Duration,
+}
+
+impl Default for GameHooks {
+    fn default() -> Self {
+        GameHooks {
+            pre_launch: None,
+            post_launch: None,
+            post_exit: None,
+            timeout: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+/// Extra CPU seconds between SIGXCPU at the soft limit and SIGKILL at the hard limit
+const CPU_LIMIT_GRACE_SECS: u64 = 1;
+
+/// A limit of zero would stop the game from starting at all
+fn deserialize_option_nonzero_limit<'d, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'d>,
+{
+    match Option::<u64>::deserialize(deserializer)? {
+        Some(0) => Err(D::Error::custom("limit must not be zero")),
+        limit => Ok(limit),
+    }
+}
+
+/// Limits applied to a game before it starts, inherited by anything it launches
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct ResourceLimits {
+    #[serde(default, deserialize_with = "deserialize_option_nonzero_limit")]
+    pub address_space_mb: Option<u64>,
+    #[serde(
+        default,
+        deserialize_with = "duration_str::deserialize_option_duration"
+    )]
+    pub cpu_time: Option<std::time::Duration>,
+    #[serde(default, deserialize_with = "deserialize_option_nonzero_limit")]
+    pub open_files: Option<u64>,
+    pub nice: Option<i32>,
+}
+
+impl ResourceLimits {
+    fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    /// Runs in the child between fork and exec, so it must stick to async-signal-safe calls
+    fn apply(&self) -> std::io::Result<()> {
+        if let Some(mb) = self.address_space_mb {
+            let bytes = mb.saturating_mul(1024 * 1024);
+            setrlimit(Resource::RLIMIT_AS, bytes, bytes)?;
+        }
+        if let Some(cpu_time) = self.cpu_time {
+            let secs = cpu_time.as_secs().max(1);
+            setrlimit(Resource::RLIMIT_CPU, secs, secs + CPU_LIMIT_GRACE_SECS)?;
+        }
+        if let Some(open_files) = self.open_files {
+            setrlimit(Resource::RLIMIT_NOFILE, open_files, open_files)?;
+        }
+        if let Some(nice) = self.nice {
+            // SAFETY: setpriority only changes the scheduling priority of this process
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
         Ok(())
+    }
+
+    /// Explains an exit caused by one of the limits. Going over the CPU time limit ends in
+    /// SIGXCPU, or SIGKILL once the grace period is up. Running out of address space or files
+    /// only shows up as the game failing in its own way, so those are only hinted at.
+    fn exceeded(&self, status: &ExitStatus) -> Option<String> {
+        let signal = status
+            .signal()
+            .and_then(|signal| Signal::try_from(signal).ok());
+        if let (Some(Signal::SIGXCPU | Signal::SIGKILL), Some(cpu_time)) = (signal, self.cpu_time) {
+            return Some(format!(
+                "exceeded its {} second CPU time limit",
+                cpu_time.as_secs().max(1)
+            ));
+        }
+
+        if status.success() {
+            return None;
+        }
+
+        let hints: Vec<String> = [
+            self.address_space_mb
+                .map(|mb| format!("its {} MB address space limit", mb)),
+            self.open_files
+                .map(|files| format!("its {} open file limit", files)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        match hints.is_empty() {
+            true => None,
+            false => Some(format!("may have hit {}", hints.join(" or "))),
+        }
+    }
+}
+
+/// The exit status reported to chat, along with any limit that was likely exceeded
+fn exit_reason(status: &ExitStatus, limits: &ResourceLimits) -> String {
+    match limits.exceeded(status) {
+        Some(exceeded) => format!("{}, {}", status, exceeded),
+        None => status.to_string(),
+    }
+}
+
+/// How to tell that a game has finished loading
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type")]
+pub enum ReadyCheck {
+    /// Ready once this long has passed since launch
+    Delay {
+        #[serde(deserialize_with = "duration_str::deserialize_duration")]
+        delay: std::time::Duration,
+    },
+    /// Ready once the game prints a line containing this text
+    Output { contains: String },
+    /// Ready once this file exists
+    File { path: std::path::PathBuf },
+}
+
+/// How often to check for a readiness file
+const READY_FILE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Events from the game runner about games that exit on their own or fail to start
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    Exited {
+        game: GameName,
+        status: String,
+        /// Delay and attempt number of the upcoming restart, if any
+        restart: Option<(std::time::Duration, u32)>,
+        max_retries: u32,
+    },
+    Restarted {
+        game: GameName,
+        attempt: u32,
+        max_retries: u32,
+    },
+    LaunchFailed {
+        game: GameName,
+        reason: String,
+    },
+    /// The game passed its readiness check, or ran out of time to
+    Ready {
+        game: GameName,
+    },
+}
+
+impl GameEvent {
+    /// The game this event is about
+    pub fn game(&self) -> &GameName {
+        match self {
+            GameEvent::Exited { game, .. }
+            | GameEvent::Restarted { game, .. }
+            | GameEvent::LaunchFailed { game, .. }
+            | GameEvent::Ready { game } => game,
+        }
+    }
+}
+
+async fn wait_on_child(child: &mut Option<Child>) -> anyhow::Result<ExitStatus> {
+    if let Some(child) = child {
+        Ok(child.wait().await?)
     } else {
         // Never resolve so that we don't spin excessively
         std::future::pending::<()>().await;
@@ -24,96 +254,544 @@ async fn wait_on_child(child: &mut Option<Child>) -> anyhow::Result<()> {
     }
 }
 
+async fn wait_until(deadline: Option<tokio::time::Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(deadline).await;
+    } else {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// The running game's process group, shared with the panic hook
+#[derive(Debug, Default)]
+struct ChildGroup {
+    pgid: AtomicI32,
+    stop_timeout_ms: AtomicU64,
+}
+
+impl ChildGroup {
+    fn set(&self, pgid: i32, stop_timeout: std::time::Duration) {
+        self.stop_timeout_ms
+            .store(stop_timeout.as_millis() as u64, Ordering::Relaxed);
+        self.pgid.store(pgid, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.pgid.store(0, Ordering::Relaxed);
+    }
+
+    /// Stops whatever is left in the group after its leader exited on its own, like an
+    /// emulator started by a wrapper script, then forgets the group
+    async fn stop_orphans(&self) {
+        let pgid = self.pgid.load(Ordering::Relaxed);
+        if pgid != 0 {
+            let pgid = Pid::from_raw(pgid);
+            let stop_timeout =
+                std::time::Duration::from_millis(self.stop_timeout_ms.load(Ordering::Relaxed));
+            if group_alive(pgid) {
+                info!("Sending sigterm to leftover processes in group {}", pgid);
+                let _ = killpg(pgid, Signal::SIGTERM);
+                let deadline = tokio::time::Instant::now() + stop_timeout;
+                if !wait_for_group_exit(pgid, deadline).await {
+                    warn!(
+                        "Process group {} did not exit within {:?}, sending sigkill",
+                        pgid, stop_timeout
+                    );
+                    let _ = killpg(pgid, Signal::SIGKILL);
+                }
+            }
+        }
+
+        self.clear();
+    }
+
+    /// Terminates the group from the panic hook, where we can't wait on the runtime
+    fn kill_blocking(&self) {
+        let pgid = self.pgid.load(Ordering::Relaxed);
+        if pgid == 0 {
+            return;
+        }
+
+        eprintln!("Sending sigterm to process group {}", pgid);
+        let pgid = Pid::from_raw(pgid);
+        let _ignored = killpg(pgid, Signal::SIGTERM);
+
+        let stop_timeout =
+            std::time::Duration::from_millis(self.stop_timeout_ms.load(Ordering::Relaxed));
+        let deadline = std::time::Instant::now() + stop_timeout;
+        while std::time::Instant::now() < deadline {
+            if !group_alive(pgid) {
+                return;
+            }
+            std::thread::sleep(GROUP_POLL_INTERVAL);
+        }
+
+        eprintln!("Process group {} is still running, sending sigkill", pgid);
+        let _ignored = killpg(pgid, Signal::SIGKILL);
+    }
+}
+
+/// Whether any process in the group is still running. Zombies don't count, orphans can
+/// sit unreaped for a while after they exit.
+fn group_alive(pgid: Pid) -> bool {
+    // Signalling the group is cheap and settles it once everything has been reaped
+    if killpg(pgid, None).is_err() {
+        return false;
+    }
+
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        // Without /proc, zombies have to count as running
+        Err(_) => return true,
+    };
+
+    entries.flatten().any(|entry| {
+        let stat = match std::fs::read_to_string(entry.path().join("stat")) {
+            Ok(stat) => stat,
+            Err(_) => return false,
+        };
+
+        // Fields after the command name, which is in parentheses and may contain spaces
+        let mut fields = stat
+            .rsplit_once(')')
+            .map_or("", |(_, fields)| fields)
+            .split_whitespace();
+        let state = fields.next();
+        let pgrp = fields.nth(1).and_then(|pgrp| pgrp.parse::<i32>().ok());
+        pgrp == Some(pgid.as_raw()) && state != Some("Z")
+    })
+}
+
+/// Waits for every process in a group to exit, returning false if the deadline passes first
+async fn wait_for_group_exit(pgid: Pid, deadline: tokio::time::Instant) -> bool {
+    loop {
+        if !group_alive(pgid) {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(GROUP_POLL_INTERVAL).await;
+    }
+}
+
 async fn stop_child(
     child: &mut Option<Child>,
-    child_pid_atomic: &Arc<AtomicI32>,
+    stop_timeout: std::time::Duration,
+    child_group: &ChildGroup,
 ) -> anyhow::Result<()> {
-    if let Some(child) = child {
+    // Taken so a later stop doesn't try to signal a process that was already reaped
+    if let Some(mut child) = child.take() {
         info!("Exiting current child");
         if let Some(pid) = child.id() {
-            let pid = nix::unistd::Pid::from_raw(pid as i32);
-            info!("Sending sigterm");
-            kill(pid, Signal::SIGTERM)?;
-            child.wait().await?;
+            // Games are spawned as group leaders, so the group shares the child's pid
+            let pgid = Pid::from_raw(pid as i32);
+            let deadline = tokio::time::Instant::now() + stop_timeout;
+            info!("Sending sigterm to process group {}", pgid);
+            // The child is still waited on, and killed if it's somehow still running
+            if let Err(e) = killpg(pgid, Signal::SIGTERM) {
+                warn!("Failed to send sigterm to process group {}: {}", pgid, e);
+            }
+
+            // Wrapper scripts can exit before the emulators they launched
+            let exited = matches!(
+                tokio::time::timeout_at(deadline, child.wait()).await,
+                Ok(Ok(_))
+            );
+            if !exited || !wait_for_group_exit(pgid, deadline).await {
+                warn!(
+                    "Process group {} did not exit within {:?}, sending sigkill",
+                    pgid, stop_timeout
+                );
+                if let Err(e) = killpg(pgid, Signal::SIGKILL) {
+                    warn!("Failed to send sigkill to process group {}: {}", pgid, e);
+                    let _ = child.start_kill();
+                }
+            }
+            if let Err(e) = child.wait().await {
+                warn!("Failed to wait for process group {}: {}", pgid, e);
+            }
         } else {
             info!("Killing process");
             child.kill().await?;
         }
 
-        child_pid_atomic.store(0, Ordering::Relaxed);
+        child_group.clear();
         info!("Child should be gone now");
     }
 
     Ok(())
 }
 
+async fn spawn_game(
+    gc: &GameCommand,
+    game_logs: Option<&GameLogConfig>,
+    child_group: &ChildGroup,
+) -> std::io::Result<(Child, Option<UnboundedReceiver<String>>)> {
+    // Its own process group lets us stop everything the game launches
+    let mut command = std::process::Command::new(&gc.command);
+    command.args(&gc.args).envs(&gc.env).process_group(0);
+    if let Some(ref cwd) = gc.cwd {
+        command.current_dir(cwd);
+    }
+    if !gc.limits.is_empty() {
+        let limits = gc.limits;
+        // SAFETY: applying the limits only makes async-signal-safe calls
+        unsafe {
+            command.pre_exec(move || limits.apply());
+        }
+    }
+    let mut command = tokio::process::Command::from(command);
+
+    // Without a log the game keeps writing to our own stdout and stderr
+    let log = match game_logs {
+        Some(cfg) => match SessionLog::start(cfg, &gc.name).await {
+            Ok(log) => {
+                command
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped());
+                Some(log)
+            }
+            Err(e) => {
+                warn!("Failed to start game log for {}: {:?}", gc.name, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Output is also piped when it's needed to tell that the game is ready
+    let (line_tx, line_rx) = match gc.ready {
+        Some(ReadyCheck::Output { .. }) => {
+            command
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped());
+            let (line_tx, line_rx) = tokio::sync::mpsc::unbounded_channel();
+            (Some(line_tx), Some(line_rx))
+        }
+        _ => (None, None),
+    };
+
+    let mut process = command.spawn()?;
+    if log.is_some() || line_tx.is_some() {
+        game_log::capture(&mut process, log, line_tx);
+    }
+
+    child_group.set(
+        match process.id() {
+            Some(pid) => pid as i32,
+            None => 0,
+        },
+        gc.stop_timeout,
+    );
+    Ok((process, line_rx))
+}
+
+async fn wait_for_ready(check: &ReadyCheck, line_rx: Option<UnboundedReceiver<String>>) {
+    match check {
+        ReadyCheck::Delay { delay } => tokio::time::sleep(*delay).await,
+        ReadyCheck::Output { contains } => {
+            if let Some(mut line_rx) = line_rx {
+                while let Some(line) = line_rx.recv().await {
+                    if line.contains(contains.as_str()) {
+                        return;
+                    }
+                }
+            }
+
+            // The game closed its output without ever matching, leave it to the timeout
+            std::future::pending::<()>().await;
+        }
+        ReadyCheck::File { path } => {
+            while !tokio::fs::try_exists(path).await.unwrap_or(false) {
+                tokio::time::sleep(READY_FILE_POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Reports when a newly launched game is ready, games without a readiness check are ready
+/// as soon as they start
+fn watch_ready(
+    gc: &GameCommand,
+    line_rx: Option<UnboundedReceiver<String>>,
+    event_tx: &UnboundedSender<GameEvent>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let check = gc.ready.clone()?;
+    let timeout = gc.ready_timeout;
+    let game = gc.name.clone();
+    let event_tx = event_tx.clone();
+
+    Some(tokio::task::spawn(async move {
+        if tokio::time::timeout(timeout, wait_for_ready(&check, line_rx))
+            .await
+            .is_err()
+        {
+            warn!(
+                "{} didn't pass its readiness check within {:?}",
+                game, timeout
+            );
+        }
+
+        info!("{} is ready", game);
+        let _ = event_tx.send(GameEvent::Ready { game });
+    }))
+}
+
+/// Runs one of a game's hooks to completion, the error explains why it failed
+async fn run_hook(kind: &str, hook: Option<&Vec<String>>, gc: &GameCommand) -> Result<(), String> {
+    let (program, args) = match hook.and_then(|hook| hook.split_first()) {
+        Some(hook) => hook,
+        None => return Ok(()),
+    };
+
+    info!("Running {} hook for {}", kind, gc.name);
+    let mut command = tokio::process::Command::new(program);
+    command.args(args).envs(&gc.env).kill_on_drop(true);
+    if let Some(ref cwd) = gc.cwd {
+        command.current_dir(cwd);
+    }
+
+    match tokio::time::timeout(gc.hooks.timeout, command.status()).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(format!("{} hook exited with {}", kind, status)),
+        Ok(Err(e)) => Err(format!("{} hook failed to start: {}", kind, e)),
+        Err(_) => Err(format!(
+            "{} hook timed out after {} seconds",
+            kind,
+            gc.hooks.timeout.as_secs_f64()
+        )),
+    }
+}
+
+/// Runs the pre-launch hook, starts the game and then runs the post-launch hook. Readiness
+/// is reported through `event_tx` by the returned task.
+async fn launch_game(
+    gc: &GameCommand,
+    game_logs: Option<&GameLogConfig>,
+    child_group: &ChildGroup,
+    event_tx: &UnboundedSender<GameEvent>,
+) -> Result<(Child, Option<tokio::task::JoinHandle<()>>), String> {
+    run_hook("pre_launch", gc.hooks.pre_launch.as_ref(), gc).await?;
+    let (process, line_rx) = spawn_game(gc, game_logs, child_group)
+        .await
+        .map_err(|e| format!("failed to start: {}", e))?;
+    let ready_watch = watch_ready(gc, line_rx, event_tx);
+
+    // The game is already up, so a failed post-launch hook is only logged
+    if let Err(e) = run_hook("post_launch", gc.hooks.post_launch.as_ref(), gc).await {
+        warn!("{} for {}", e, gc.name);
+    }
+
+    Ok((process, ready_watch))
+}
+
+async fn run_post_exit_hook(gc: &GameCommand) {
+    if let Err(e) = run_hook("post_exit", gc.hooks.post_exit.as_ref(), gc).await {
+        warn!("{} for {}", e, gc.name);
+    }
+}
+
+/// Stops the running game, running its post-exit hook if it was still up
+async fn stop_game(
+    current_process: &mut Option<Child>,
+    supervised: &mut Option<Supervised>,
+    child_group: &ChildGroup,
+    stop_after: Option<tokio::time::Instant>,
+) -> anyhow::Result<()> {
+    let running = current_process.is_some();
+    if let Some(stop_after) = stop_after.filter(|_| running) {
+        info!("Waiting before stopping the game");
+        tokio::time::sleep_until(stop_after).await;
+    }
+
+    let game = supervised.take();
+    let stop_timeout = game
+        .as_ref()
+        .map_or(DEFAULT_STOP_TIMEOUT, |game| game.command.stop_timeout);
+
+    stop_child(current_process, stop_timeout, child_group).await?;
+    if let Some(game) = game.filter(|_| running) {
+        run_post_exit_hook(&game.command).await;
+    }
+
+    Ok(())
+}
+
+/// The game that should be running and how often it's been restarted
+struct Supervised {
+    command: GameCommand,
+    started_at: tokio::time::Instant,
+    attempts: u32,
+    restart_at: Option<tokio::time::Instant>,
+    /// Reports when the current launch is ready, stopped along with the game
+    ready_watch: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for Supervised {
+    fn drop(&mut self) {
+        if let Some(ref ready_watch) = self.ready_watch {
+            ready_watch.abort();
+        }
+    }
+}
+
+impl Supervised {
+    fn new(command: GameCommand, ready_watch: Option<tokio::task::JoinHandle<()>>) -> Self {
+        Supervised {
+            command,
+            started_at: tokio::time::Instant::now(),
+            attempts: 0,
+            restart_at: None,
+            ready_watch,
+        }
+    }
+
+    /// Schedules a restart if the policy allows it, returning the event to report
+    fn exited(&mut self, status: String, restart: bool) -> GameEvent {
+        let settings = self.command.restart;
+        if self.started_at.elapsed() >= RESTART_RESET_AFTER {
+            self.attempts = 0;
+        }
+
+        let restart = if restart && self.attempts < settings.max_retries {
+            let delay = settings.backoff * 2u32.saturating_pow(self.attempts);
+            self.attempts += 1;
+            self.restart_at = Some(tokio::time::Instant::now() + delay);
+            Some((delay, self.attempts))
+        } else {
+            None
+        };
+
+        GameEvent::Exited {
+            game: self.command.name.clone(),
+            status,
+            restart,
+            max_retries: settings.max_retries,
+        }
+    }
+}
+
 async fn game_runner_loop(
     mut rx: tokio::sync::mpsc::Receiver<GameRunner>,
-    child_pid_atomic: Arc<AtomicI32>,
+    event_tx: UnboundedSender<GameEvent>,
+    game_logs: Option<GameLogConfig>,
+    child_group: Arc<ChildGroup>,
 ) -> anyhow::Result<()> {
     let mut current_process: Option<tokio::process::Child> = None;
+    let mut supervised: Option<Supervised> = None;
+    let mut stop_after: Option<tokio::time::Instant> = None;
 
     loop {
         tokio::select! {
             cmd = rx.recv() => {
                 match cmd {
-                    Some(GameRunner::Stop) => stop_child(&mut current_process, &child_pid_atomic).await?,
+                    Some(GameRunner::DelayStop(delay)) => {
+                        stop_after = Some(tokio::time::Instant::now() + delay);
+                    }
+                    Some(GameRunner::Stop) => {
+                        stop_game(&mut current_process, &mut supervised, &child_group, stop_after.take()).await?;
+                    }
                     Some(GameRunner::SwitchTo(gc)) => {
-                        stop_child(&mut current_process, &child_pid_atomic).await?;
-                        let new_process = tokio::process::Command::new(gc.command)
-                            .args(gc.args)
-                            .spawn()?;
-                        child_pid_atomic.store(match new_process.id() {
-                            Some(pid) => pid as i32,
-                            None => 0,
-                        }, Ordering::Relaxed);
-                        current_process = Some(new_process);
+                        stop_game(&mut current_process, &mut supervised, &child_group, stop_after.take()).await?;
+                        match launch_game(&gc, game_logs.as_ref(), &child_group, &event_tx).await {
+                            Ok((process, ready_watch)) => {
+                                current_process = Some(process);
+                                supervised = Some(Supervised::new(*gc, ready_watch));
+                            }
+                            Err(reason) => {
+                                warn!("Failed to launch {}: {}", gc.name, reason);
+                                let _ = event_tx.send(GameEvent::LaunchFailed {
+                                    game: gc.name.clone(),
+                                    reason,
+                                });
+                            }
+                        }
                     }
                     _ => {
                         tracing::info!("Game runner done");
-                        stop_child(&mut current_process, &child_pid_atomic).await?;
+                        stop_game(&mut current_process, &mut supervised, &child_group, stop_after.take()).await?;
                         break Ok(());
                     },
                 }
             },
-            _ = wait_on_child(&mut current_process) => {
-                info!("Child exited");
+            status = wait_on_child(&mut current_process) => {
+                let status = status?;
+                info!("Child exited with {}", status);
                 current_process = None;
+                child_group.stop_orphans().await;
+
+                if let Some(ref mut game) = supervised {
+                    run_post_exit_hook(&game.command).await;
+                    let restart = game.command.restart.should_restart(&status);
+                    let event = game.exited(exit_reason(&status, &game.command.limits), restart);
+                    if game.restart_at.is_none() {
+                        supervised = None;
+                    }
+                    let _ = event_tx.send(event);
+                }
+            },
+            _ = wait_until(supervised.as_ref().and_then(|game| game.restart_at)) => {
+                if let Some(ref mut game) = supervised {
+                    game.restart_at = None;
+                    game.started_at = tokio::time::Instant::now();
+                    match launch_game(&game.command, game_logs.as_ref(), &child_group, &event_tx).await {
+                        Ok((process, ready_watch)) => {
+                            info!("Restarted game, attempt {}", game.attempts);
+                            current_process = Some(process);
+                            if let Some(old) = std::mem::replace(&mut game.ready_watch, ready_watch) {
+                                old.abort();
+                            }
+                            let _ = event_tx.send(GameEvent::Restarted {
+                                game: game.command.name.clone(),
+                                attempt: game.attempts,
+                                max_retries: game.command.restart.max_retries,
+                            });
+                        }
+                        Err(reason) => {
+                            warn!("Failed to restart game: {}", reason);
+                            let event = game.exited(reason, true);
+                            if game.restart_at.is_none() {
+                                supervised = None;
+                            }
+                            let _ = event_tx.send(event);
+                        }
+                    }
+                }
             },
         }
     }
 }
 
-pub fn run_game_runner() -> (
+pub fn run_game_runner(
+    game_logs: Option<GameLogConfig>,
+) -> (
     tokio::task::JoinHandle<anyhow::Result<()>>,
     tokio::sync::mpsc::Sender<GameRunner>,
+    tokio::sync::mpsc::UnboundedReceiver<GameEvent>,
 ) {
     let (tx, rx) = tokio::sync::mpsc::channel(20);
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
 
-    let child_pid_atomic = Arc::new(AtomicI32::new(0));
+    let child_group = Arc::new(ChildGroup::default());
 
     {
-        let child_pid_atomic = child_pid_atomic.clone();
+        let child_group = child_group.clone();
         let panic_handler = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |panic_info| {
-            eprintln!("Panic handler invoked, killing child process");
-            let pid = child_pid_atomic.load(Ordering::Relaxed);
-            if pid != 0 {
-                eprintln!("Sending kill signal to {}", pid);
-                let pid = nix::unistd::Pid::from_raw(pid);
-                let _ignored = kill(pid, Signal::SIGTERM);
-            }
+            eprintln!("Panic handler invoked, killing child process group");
+            child_group.kill_blocking();
 
             panic_handler(panic_info);
         }));
     }
 
     let handle = tokio::task::spawn(async move {
-        game_runner_loop(rx, child_pid_atomic).await?;
+        game_runner_loop(rx, event_tx, game_logs, child_group).await?;
         Ok(())
     });
 
-    (handle, tx)
+    (handle, tx, event_rx)
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -236,3 +914,471 @@ mod sfx_player {
         assert_eq!(SubEvent(2147483647).to_file(&cfg), Some(&"100".to_owned()));
     }
 }
+
+#[cfg(test)]
+mod supervisor {
+    use std::sync::{atomic::Ordering, Arc};
+
+    use nix::unistd::Pid;
+
+    use super::{
+        game_runner_loop, group_alive, ChildGroup, GameEvent, GameRunner, ReadyCheck,
+        ResourceLimits, RestartPolicy, RestartSettings,
+    };
+    use crate::config::GameCommand;
+
+    fn game_command(command: &str, args: &[&str], policy: RestartPolicy) -> GameCommand {
+        GameCommand {
+            name: "game".to_owned(),
+            command: command.to_owned(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: Default::default(),
+            cwd: None,
+            hooks: Default::default(),
+            ready: None,
+            ready_timeout: std::time::Duration::from_secs(1),
+            limits: Default::default(),
+            restart: RestartSettings {
+                policy,
+                max_retries: 2,
+                backoff: std::time::Duration::from_millis(10),
+            },
+            stop_timeout: std::time::Duration::from_millis(200),
+        }
+    }
+
+    async fn run_until_given_up(command: &str, policy: RestartPolicy) -> Vec<GameEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let runner = tokio::task::spawn(game_runner_loop(rx, event_tx, None, Arc::default()));
+
+        tx.send(GameRunner::SwitchTo(Box::new(game_command(
+            command,
+            &[],
+            policy,
+        ))))
+        .await
+        .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = event_rx.recv().await {
+            let done = matches!(event, GameEvent::Exited { restart: None, .. });
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+
+        drop(tx);
+        runner.await.unwrap().unwrap();
+        events
+    }
+
+    #[tokio::test]
+    async fn failed_games_are_restarted_with_backoff() {
+        let events = run_until_given_up("false", RestartPolicy::OnFailure).await;
+        let exited = |restart| GameEvent::Exited {
+            game: "game".to_owned(),
+            status: "exit status: 1".to_owned(),
+            restart,
+            max_retries: 2,
+        };
+
+        assert_eq!(
+            events,
+            vec![
+                exited(Some((std::time::Duration::from_millis(10), 1))),
+                GameEvent::Restarted {
+                    game: "game".to_owned(),
+                    attempt: 1,
+                    max_retries: 2
+                },
+                exited(Some((std::time::Duration::from_millis(20), 2))),
+                GameEvent::Restarted {
+                    game: "game".to_owned(),
+                    attempt: 2,
+                    max_retries: 2
+                },
+                exited(None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn successful_exits_are_not_restarted_on_failure_policy() {
+        let events = run_until_given_up("true", RestartPolicy::OnFailure).await;
+        assert_eq!(
+            events,
+            vec![GameEvent::Exited {
+                game: "game".to_owned(),
+                status: "exit status: 0".to_owned(),
+                restart: None,
+                max_retries: 2,
+            }]
+        );
+
+        let events = run_until_given_up("true", RestartPolicy::Always).await;
+        assert_eq!(events.len(), 5);
+    }
+
+    async fn stop_game(script: &str) -> std::time::Duration {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let child_group = Arc::new(ChildGroup::default());
+        let runner = tokio::task::spawn(game_runner_loop(rx, event_tx, None, child_group.clone()));
+
+        tx.send(GameRunner::SwitchTo(Box::new(game_command(
+            "sh",
+            &["-c", script],
+            RestartPolicy::Never,
+        ))))
+        .await
+        .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let pgid = Pid::from_raw(child_group.pgid.load(Ordering::Relaxed));
+
+        let stopping = std::time::Instant::now();
+        tx.send(GameRunner::Stop).await.unwrap();
+        drop(tx);
+        runner.await.unwrap().unwrap();
+
+        // Nothing in the group survives
+        assert!(!group_alive(pgid));
+        stopping.elapsed()
+    }
+
+    #[tokio::test]
+    async fn whole_process_group_is_stopped() {
+        // The shell exits on SIGTERM, leaving the background sleep behind if only it was signalled
+        let elapsed = stop_game("sleep 30 & wait").await;
+        assert!(elapsed < std::time::Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn process_group_is_stopped_when_leader_exits() {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let child_group = Arc::new(ChildGroup::default());
+        let runner = tokio::task::spawn(game_runner_loop(rx, event_tx, None, child_group.clone()));
+
+        // The shell exits by itself, leaving the background sleep behind in its group
+        tx.send(GameRunner::SwitchTo(Box::new(game_command(
+            "sh",
+            &["-c", "sleep 30 & sleep 0.2"],
+            RestartPolicy::Never,
+        ))))
+        .await
+        .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let pgid = Pid::from_raw(child_group.pgid.load(Ordering::Relaxed));
+
+        assert!(matches!(
+            event_rx.recv().await.unwrap(),
+            GameEvent::Exited { .. }
+        ));
+        assert!(!group_alive(pgid));
+        assert_eq!(child_group.pgid.load(Ordering::Relaxed), 0);
+
+        drop(tx);
+        runner.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn delayed_stops_wait_for_the_delay() {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let runner = tokio::task::spawn(game_runner_loop(rx, event_tx, None, Arc::default()));
+
+        tx.send(GameRunner::SwitchTo(Box::new(game_command(
+            "sleep",
+            &["30"],
+            RestartPolicy::Never,
+        ))))
+        .await
+        .unwrap();
+
+        let stopping = std::time::Instant::now();
+        tx.send(GameRunner::DelayStop(std::time::Duration::from_millis(300)))
+            .await
+            .unwrap();
+        tx.send(GameRunner::Stop).await.unwrap();
+        drop(tx);
+        runner.await.unwrap().unwrap();
+
+        let elapsed = stopping.elapsed();
+        assert!(elapsed >= std::time::Duration::from_millis(300));
+        assert!(elapsed < std::time::Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn hung_games_are_killed_after_stop_timeout() {
+        let elapsed = stop_game("trap '' TERM; sleep 30").await;
+        assert!(elapsed >= std::time::Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn games_get_their_env_and_cwd() {
+        let game_logs = crate::game_log::GameLogConfig {
+            dir: std::env::temp_dir().join(format!("twitch-gamepad-env-{}", std::process::id())),
+            ..Default::default()
+        };
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let runner = tokio::task::spawn(game_runner_loop(
+            rx,
+            event_tx,
+            Some(game_logs.clone()),
+            Arc::default(),
+        ));
+
+        let mut command = game_command("sh", &["-c", "echo $GAME_VAR; pwd"], RestartPolicy::Never);
+        command
+            .env
+            .insert("GAME_VAR".to_owned(), "from env".to_owned());
+        command.cwd = Some("/".into());
+        tx.send(GameRunner::SwitchTo(Box::new(command)))
+            .await
+            .unwrap();
+
+        event_rx.recv().await.unwrap();
+        drop(tx);
+        runner.await.unwrap().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let output = crate::game_log::previous_session_tail(&game_logs, "game", false, 5)
+            .await
+            .unwrap();
+        assert_eq!(output, Some(vec!["from env".to_owned(), "/".to_owned()]));
+        std::fs::remove_dir_all(&game_logs.dir).unwrap();
+    }
+
+    async fn launch_event(command: GameCommand) -> (GameEvent, Arc<ChildGroup>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        let child_group = Arc::new(ChildGroup::default());
+        let runner = tokio::task::spawn(game_runner_loop(rx, event_tx, None, child_group.clone()));
+
+        tx.send(GameRunner::SwitchTo(Box::new(command)))
+            .await
+            .unwrap();
+        let event = event_rx.recv().await.unwrap();
+
+        drop(tx);
+        runner.await.unwrap().unwrap();
+        (event, child_group)
+    }
+
+    #[tokio::test]
+    async fn failed_pre_launch_hook_aborts_launch() {
+        let mut command = game_command("sleep", &["30"], RestartPolicy::Never);
+        command.hooks.pre_launch = Some(vec!["false".to_owned()]);
+        let (event, child_group) = launch_event(command).await;
+
+        assert_eq!(
+            event,
+            GameEvent::LaunchFailed {
+                game: "game".to_owned(),
+                reason: "pre_launch hook exited with exit status: 1".to_owned()
+            }
+        );
+        assert_eq!(child_group.pgid.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn hooks_are_killed_after_timeout() {
+        let mut command = game_command("sleep", &["30"], RestartPolicy::Never);
+        command.hooks.pre_launch = Some(vec!["sleep".to_owned(), "30".to_owned()]);
+        command.hooks.timeout = std::time::Duration::from_millis(100);
+        let (event, _) = launch_event(command).await;
+
+        assert_eq!(
+            event,
+            GameEvent::LaunchFailed {
+                game: "game".to_owned(),
+                reason: "pre_launch hook timed out after 0.1 seconds".to_owned()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn post_launch_and_post_exit_hooks_run() {
+        let dir = std::env::temp_dir().join(format!("twitch-gamepad-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let hook = |name: &str| {
+            Some(vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                format!("echo {} >> hooks.txt", name),
+            ])
+        };
+        let mut command = game_command("true", &[], RestartPolicy::Never);
+        command.cwd = Some(dir.clone());
+        command.hooks.post_launch = hook("post_launch");
+        command.hooks.post_exit = hook("post_exit");
+        let (event, _) = launch_event(command).await;
+
+        assert!(matches!(event, GameEvent::Exited { .. }));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("hooks.txt")).unwrap(),
+            "post_launch\npost_exit\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn games_are_ready_once_output_matches() {
+        let mut command = game_command(
+            "sh",
+            &["-c", "echo booting; sleep 0.1; echo game loaded; sleep 30"],
+            RestartPolicy::Never,
+        );
+        command.ready = Some(ReadyCheck::Output {
+            contains: "loaded".to_owned(),
+        });
+        command.ready_timeout = std::time::Duration::from_secs(5);
+
+        let started = std::time::Instant::now();
+        let (event, _) = launch_event(command).await;
+        assert_eq!(
+            event,
+            GameEvent::Ready {
+                game: "game".to_owned()
+            }
+        );
+        assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn ready_checks_time_out() {
+        let mut command = game_command("sleep", &["30"], RestartPolicy::Never);
+        command.ready = Some(ReadyCheck::File {
+            path: "/nonexistent/ready".into(),
+        });
+        command.ready_timeout = std::time::Duration::from_millis(100);
+
+        let (event, _) = launch_event(command).await;
+        assert_eq!(
+            event,
+            GameEvent::Ready {
+                game: "game".to_owned()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn resource_limits_are_applied() {
+        let mut command = game_command(
+            "sh",
+            &[
+                "-c",
+                r#"[ "$(ulimit -n)" = 32 ] && [ "$(ulimit -v)" = 1048576 ] && [ "$(nice)" = 5 ]"#,
+            ],
+            RestartPolicy::Never,
+        );
+        command.limits = ResourceLimits {
+            address_space_mb: Some(1024),
+            open_files: Some(32),
+            nice: Some(5),
+            ..Default::default()
+        };
+
+        let (event, _) = launch_event(command).await;
+        assert_eq!(
+            event,
+            GameEvent::Exited {
+                game: "game".to_owned(),
+                status: "exit status: 0".to_owned(),
+                restart: None,
+                max_retries: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        let parse = |limits: &str| toml::from_str::<ResourceLimits>(limits);
+
+        assert!(parse("address_space_mb = 0").is_err());
+        assert!(parse("open_files = 0").is_err());
+        assert_eq!(
+            parse("open_files = 64").unwrap(),
+            ResourceLimits {
+                open_files: Some(64),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn exceeded_limits_are_reported() {
+        let mut command = game_command("sh", &["-c", "while :; do :; done"], RestartPolicy::Never);
+        command.limits.cpu_time = Some(std::time::Duration::from_secs(1));
+
+        let (event, _) = launch_event(command).await;
+        let status = match event {
+            GameEvent::Exited { status, .. } => status,
+            event => panic!("unexpected event {:?}", event),
+        };
+
+        // Whether a core was dumped depends on the environment
+        assert!(status.starts_with("signal: 24 (SIGXCPU)"));
+        assert!(status.ends_with(", exceeded its 1 second CPU time limit"));
+    }
+
+    async fn exit_status(script: &str, limits: ResourceLimits) -> String {
+        let mut command = game_command("sh", &["-c", script], RestartPolicy::Never);
+        command.limits = limits;
+
+        match launch_event(command).await.0 {
+            GameEvent::Exited { status, .. } => status,
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn cpu_time_kills_are_reported() {
+        // Stands in for the hard limit, which kills with SIGKILL after the grace period
+        let status = exit_status(
+            "kill -KILL $$",
+            ResourceLimits {
+                cpu_time: Some(std::time::Duration::from_secs(30)),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(
+            status,
+            "signal: 9 (SIGKILL), exceeded its 30 second CPU time limit"
+        );
+    }
+
+    #[tokio::test]
+    async fn possible_limit_breaches_are_hinted_at() {
+        let limits = ResourceLimits {
+            address_space_mb: Some(1024),
+            ..Default::default()
+        };
+
+        let status = exit_status("kill -SEGV $$", limits).await;
+        assert!(status.starts_with("signal: 11 (SIGSEGV)"));
+        assert!(status.ends_with(", may have hit its 1024 MB address space limit"));
+
+        let status = exit_status(
+            "exit 1",
+            ResourceLimits {
+                open_files: Some(32),
+                ..limits
+            },
+        )
+        .await;
+        assert_eq!(
+            status,
+            "exit status: 1, may have hit its 1024 MB address space limit or its 32 open file limit"
+        );
+
+        assert_eq!(exit_status("exit 0", limits).await, "exit status: 0");
+        let status = exit_status("kill -SEGV $$", ResourceLimits::default()).await;
+        assert!(!status.contains("may have hit"), "{}", status);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use crate::config::{ConstructedGameInfo, GameName};
+
+pub type GameList = BTreeMap<GameName, ConstructedGameInfo>;
+
+pub enum GameMatch<'a> {
+    /// The name or display name matches, ignoring case and punctuation
+    Exact(&'a ConstructedGameInfo),
+    /// The only game that's a near miss
+    Close(&'a ConstructedGameInfo),
+    /// Several games are near misses
+    Ambiguous(Vec<&'a ConstructedGameInfo>),
+    NotFound,
+}
+
+/// Lowercases and replaces punctuation with spaces, so `Super Game (USA)` matches `super game usa`
+fn normalize(text: &str) -> String {
+    let text: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            row.push(substitute.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+
+    prev[b.len()]
+}
+
+fn names(game: &ConstructedGameInfo) -> [String; 2] {
+    [normalize(&game.name), normalize(&game.display_name)]
+}
+
+/// Finds the game a moderator most likely meant
+pub fn find_game<'a>(games: &'a GameList, query: &str) -> GameMatch<'a> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return GameMatch::NotFound;
+    }
+
+    if let Some(game) = games.values().find(|game| names(game).contains(&query)) {
+        return GameMatch::Exact(game);
+    }
+
+    let containing: Vec<&ConstructedGameInfo> = games
+        .values()
+        .filter(|game| names(game).iter().any(|name| name.contains(&query)))
+        .collect();
+    match containing[..] {
+        [game] => return GameMatch::Close(game),
+        [_, _, ..] => return GameMatch::Ambiguous(containing),
+        [] => {}
+    }
+
+    // Allow about one typo for every four characters
+    let max_distance = (query.chars().count() / 4).max(1);
+    let distances: Vec<(usize, &ConstructedGameInfo)> = games
+        .values()
+        .filter_map(|game| {
+            names(game)
+                .iter()
+                .map(|name| edit_distance(&query, name))
+                .min()
+                .filter(|distance| *distance <= max_distance)
+                .map(|distance| (distance, game))
+        })
+        .collect();
+
+    let best = match distances.iter().map(|(distance, _)| *distance).min() {
+        Some(best) => best,
+        None => return GameMatch::NotFound,
+    };
+    let mut closest: Vec<&ConstructedGameInfo> = distances
+        .into_iter()
+        .filter(|(distance, _)| *distance == best)
+        .map(|(_, game)| game)
+        .collect();
+
+    match closest.len() {
+        1 => GameMatch::Close(closest.remove(0)),
+        _ => GameMatch::Ambiguous(closest),
+    }
+}
+
+/// Games with every word of `text` in their name, display name or tags
+pub fn search_games<'a>(games: &'a GameList, text: &str) -> Vec<&'a ConstructedGameInfo> {
+    let words: Vec<String> = normalize(text)
+        .split_whitespace()
+        .map(|word| word.to_owned())
+        .collect();
+
+    games
+        .values()
+        .filter(|game| {
+            let mut haystack = names(game).join(" ");
+            for tag in game.tags.iter() {
+                haystack.push(' ');
+                haystack.push_str(&normalize(tag));
+            }
+            words.iter().all(|word| haystack.contains(word.as_str()))
+        })
+        .collect()
+}
+
+pub fn games_tagged<'a>(games: &'a GameList, tag: &str) -> Vec<&'a ConstructedGameInfo> {
+    games
+        .values()
+        .filter(|game| game.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        .collect()
+}
+
+#[cfg(test)]
+mod game_search_test {
+    use crate::config::{Config, GameCommandString, GameInfo};
+
+    use super::{edit_distance, find_game, games_tagged, search_games, GameList, GameMatch};
+
+    fn games() -> GameList {
+        let mut cfg: Config =
+            toml::from_str("[twitch]\nchannel_name = \"\"\n[twitch.auth]\ntype = \"Anonymous\"\n")
+                .unwrap();
+
+        let game = |display_name: Option<&str>, tags: &[&str]| GameInfo {
+            command: GameCommandString("game".to_owned()),
+            display_name: display_name.map(|name| name.to_owned()),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            ..Default::default()
+        };
+
+        cfg.games = Some(
+            [
+                (
+                    "smw",
+                    game(Some("Super Mario World"), &["snes", "platformer"]),
+                ),
+                ("Super Metroid", game(None, &["SNES", "metroidvania"])),
+                ("Mario Kart 64", game(None, &["n64", "racing"])),
+                ("zelda-oot", game(Some("Ocarina of Time"), &["n64"])),
+            ]
+            .into_iter()
+            .map(|(name, gi)| (name.to_owned(), gi))
+            .collect(),
+        );
+        cfg.game_command_list()
+    }
+
+    fn name(game_match: GameMatch) -> String {
+        match game_match {
+            GameMatch::Exact(game) => format!("exact {}", game.name),
+            GameMatch::Close(game) => format!("close {}", game.name),
+            GameMatch::Ambiguous(games) => {
+                let names: Vec<&str> = games.iter().map(|game| game.name.as_str()).collect();
+                format!("ambiguous {}", names.join(", "))
+            }
+            GameMatch::NotFound => "not found".to_owned(),
+        }
+    }
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(edit_distance("metroid", "metroid"), 0);
+        assert_eq!(edit_distance("metriod", "metroid"), 2);
+        assert_eq!(edit_distance("mario", "marios"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn names_are_matched_loosely() {
+        let games = games();
+        assert_eq!(name(find_game(&games, "smw")), "exact smw");
+        assert_eq!(name(find_game(&games, "super mario world")), "exact smw");
+        assert_eq!(
+            name(find_game(&games, "mario kart-64")),
+            "exact Mario Kart 64"
+        );
+        assert_eq!(name(find_game(&games, "ocarina")), "close zelda-oot");
+        assert_eq!(
+            name(find_game(&games, "super metriod")),
+            "close Super Metroid"
+        );
+        assert_eq!(
+            name(find_game(&games, "mario")),
+            "ambiguous Mario Kart 64, smw"
+        );
+        assert_eq!(name(find_game(&games, "tetris")), "not found");
+        assert_eq!(name(find_game(&games, "  ")), "not found");
+    }
+
+    #[test]
+    fn games_are_searched_and_filtered() {
+        let games = games();
+        let names = |found: Vec<&crate::config::ConstructedGameInfo>| -> Vec<String> {
+            found.iter().map(|game| game.name.clone()).collect()
+        };
+
+        assert_eq!(
+            names(search_games(&games, "super")),
+            vec!["Super Metroid", "smw"]
+        );
+        assert_eq!(names(search_games(&games, "n64 time")), vec!["zelda-oot"]);
+        assert_eq!(
+            names(games_tagged(&games, "snes")),
+            vec!["Super Metroid", "smw"]
+        );
+        assert!(games_tagged(&games, "gba").is_empty());
+    }
+}
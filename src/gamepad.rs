@@ -1,17 +1,19 @@
 use std::collections::VecDeque;
 use strum::IntoEnumIterator;
 
-use crate::command::{Movement, MovementPacket};
+use crate::command::{Axis, Hold, Movement, MovementPacket, Repeat, AXIS_MAX, AXIS_MIN};
 use tokio::{
     select,
     sync::mpsc::{Receiver, Sender},
+    time::{Duration, Instant},
 };
-use tracing::info;
+use tracing::{info, warn};
 use uinput::event::{absolute, controller};
 
 pub trait Gamepad {
     fn press(&mut self, movement: Movement) -> anyhow::Result<()>;
     fn release(&mut self, movement: Movement) -> anyhow::Result<()>;
+    fn set_axis(&mut self, axis: Axis, value: i32) -> anyhow::Result<()>;
 }
 
 pub struct UinputGamepad {
@@ -24,19 +26,40 @@ impl UinputGamepad {
             .name("Twitch Gamepad")?
             .event(controller::Controller::All)?
             .event(absolute::Absolute::Position(absolute::Position::X))?
-            .min(0)
-            .max(255)
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
             .fuzz(0)
             .flat(0)
             .event(absolute::Absolute::Position(absolute::Position::Y))?
-            .min(0)
-            .max(255)
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
+            .fuzz(0)
+            .flat(0)
+            .event(absolute::Absolute::Position(absolute::Position::RX))?
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
+            .fuzz(0)
+            .flat(0)
+            .event(absolute::Absolute::Position(absolute::Position::RY))?
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
+            .fuzz(0)
+            .flat(0)
+            .event(absolute::Absolute::Position(absolute::Position::Z))?
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
+            .fuzz(0)
+            .flat(0)
+            .event(absolute::Absolute::Position(absolute::Position::RZ))?
+            .min(AXIS_MIN)
+            .max(AXIS_MAX)
             .fuzz(0)
             .flat(0)
             .create()?;
 
-        gamepad.send(absolute::Absolute::Position(absolute::Position::X), 128)?;
-        gamepad.send(absolute::Absolute::Position(absolute::Position::Y), 128)?;
+        for axis in Axis::iter() {
+            gamepad.send(Self::map_axis(&axis), axis.rest_value())?;
+        }
         gamepad.synchronize()?;
 
         Ok(UinputGamepad { gamepad })
@@ -63,6 +86,18 @@ impl UinputGamepad {
             Mode => Controller::GamePad(GamePad::Mode),
         }
     }
+
+    fn map_axis(axis: &Axis) -> absolute::Absolute {
+        use absolute::{Absolute, Position};
+        match axis {
+            Axis::X => Absolute::Position(Position::X),
+            Axis::Y => Absolute::Position(Position::Y),
+            Axis::RX => Absolute::Position(Position::RX),
+            Axis::RY => Absolute::Position(Position::RY),
+            Axis::Z => Absolute::Position(Position::Z),
+            Axis::RZ => Absolute::Position(Position::RZ),
+        }
+    }
 }
 
 impl Gamepad for UinputGamepad {
@@ -81,8 +116,52 @@ impl Gamepad for UinputGamepad {
         self.gamepad.synchronize()?;
         Ok(())
     }
+
+    fn set_axis(&mut self, axis: Axis, value: i32) -> anyhow::Result<()> {
+        let cmd = Self::map_axis(&axis);
+
+        self.gamepad.send(cmd, value)?;
+        self.gamepad.synchronize()?;
+        Ok(())
+    }
 }
 
+/// The steps a packet is played as, each press of a repeated packet is followed by a pause
+fn packet_steps(packet: &MovementPacket) -> Vec<MovementPacket> {
+    let step = MovementPacket {
+        chain: vec![],
+        repeat: None,
+        ..packet.clone()
+    };
+    let presses = match packet.repeat {
+        Some(Repeat::Times(presses)) => presses,
+        Some(Repeat::For(duration) | Repeat::Turbo(duration)) => {
+            duration / (packet.duration * 2).max(1)
+        }
+        None => 1,
+    };
+
+    let pause = MovementPacket {
+        movements: vec![],
+        axes: vec![],
+        ..step.clone()
+    };
+    let mut steps = vec![step.clone()];
+    for _ in 1..presses {
+        steps.push(pause.clone());
+        steps.push(step.clone());
+    }
+
+    steps
+}
+
+/// How long inputs stay released before they're pressed again, so games see the release
+const RELEASE_GAP: Duration = Duration::from_millis(10);
+
+/// Packets waiting beyond this, e.g. while a chain plays, push out the oldest ones. Blocking
+/// packets, like save and load hotkeys, are never pushed out.
+const MAX_QUEUED_PACKETS: usize = 20;
+
 async fn blocking_movement<G: Gamepad>(
     gamepad: &mut G,
     packet: &MovementPacket,
@@ -90,6 +169,7 @@ async fn blocking_movement<G: Gamepad>(
     info!("Executing blocking movement: {:?}", packet);
     let MovementPacket {
         movements,
+        axes,
         duration,
         stagger,
         ..
@@ -103,8 +183,16 @@ async fn blocking_movement<G: Gamepad>(
         }
     }
 
+    for (axis, value) in axes.iter() {
+        gamepad.set_axis(*axis, *value)?;
+    }
+
     tokio::time::sleep(tokio::time::Duration::from_millis(*duration)).await;
 
+    for (axis, _) in axes.iter() {
+        gamepad.set_axis(*axis, axis.rest_value())?;
+    }
+
     for movement in movements.iter().rev() {
         gamepad.release(*movement)?;
 
@@ -119,26 +207,152 @@ async fn blocking_movement<G: Gamepad>(
 }
 
 struct RunnerState<'a, G: Gamepad> {
-    update_interval_ms: u64,
     gamepad: &'a mut G,
-    movement_time_remaining: Box<[u64]>,
-    apply_next_tick: Option<MovementPacket>,
+    /// When each pressed movement is released
+    movement_deadlines: Box<[Option<Instant>]>,
+    axis_deadlines: Box<[Option<Instant>]>,
+    /// When held buttons are released, held buttons don't hold up other packets
+    hold_deadlines: Box<[Option<Instant>]>,
+    /// Pressed once the inputs it interrupted have been released for `RELEASE_GAP`
+    apply_next: Option<MovementPacket>,
     packet_queue: VecDeque<MovementPacket>,
-    interval: tokio::time::Interval,
+    /// The chain step being played, the next step starts once it's released
+    chain_step: Option<MovementPacket>,
+    chain: VecDeque<MovementPacket>,
+    /// When a chain step without inputs, which only waits, is over
+    chain_pause_until: Option<Instant>,
+    /// Released inputs aren't pressed again by queued packets or chain steps before this
+    settled_at: Instant,
     draining: bool,
 }
 
 impl<'a, G: Gamepad> RunnerState<'a, G> {
-    fn time_remaining_empty(&self) -> bool {
-        self.movement_time_remaining
+    fn deadlines_empty(&self) -> bool {
+        self.movement_deadlines
             .iter()
-            .all(|remaining| *remaining == 0)
+            .chain(self.axis_deadlines.iter())
+            .all(Option::is_none)
     }
 
-    fn cancel_if_active(&mut self, movement: Movement) -> anyhow::Result<bool> {
-        if self.movement_time_remaining[movement as usize] > 0 {
-            self.movement_time_remaining[movement as usize] = 0;
+    fn chain_active(&self) -> bool {
+        self.chain_step.is_some()
+    }
+
+    /// The next time something has to be pressed or released
+    fn next_deadline(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let waiting =
+            self.apply_next.is_some() || self.chain_active() || !self.packet_queue.is_empty();
+        let waits = [
+            Some(self.settled_at).filter(|_| waiting),
+            self.chain_pause_until,
+        ];
+
+        self.movement_deadlines
+            .iter()
+            .chain(self.axis_deadlines.iter())
+            .chain(self.hold_deadlines.iter())
+            .flatten()
+            .copied()
+            // Waits that have passed don't change until something else happens
+            .chain(waits.into_iter().flatten().filter(|wait| *wait > now))
+            .min()
+    }
+
+    fn mark_released(&mut self) {
+        self.settled_at = Instant::now() + RELEASE_GAP;
+    }
+
+    /// Tracks the rest of a packet's chain and repeats once its first press has been applied,
+    /// other packets are queued until they've been played
+    fn start_chain(&mut self, packet: &MovementPacket) {
+        if packet.chain.is_empty() && packet.repeat.is_none() {
+            return;
+        }
+
+        let mut steps = packet_steps(packet);
+        steps.extend(packet.chain.iter().cloned());
+        info!("Starting chain of {} steps", steps.len());
+        self.chain_step = Some(steps.remove(0));
+        self.chain = steps.into();
+    }
+
+    fn apply_chain_step(&mut self, step: &MovementPacket) -> anyhow::Result<()> {
+        let mut steps = packet_steps(step);
+        let step = steps.remove(0);
+        for next in steps.into_iter().rev() {
+            self.chain.push_front(next);
+        }
+
+        info!("Executing next in chain: {:?}", step);
+        self.apply_packet(&step)?;
+        // The gap after the last step was released counts towards the pause
+        if step.movements.is_empty() && step.axes.is_empty() {
+            let pause = Duration::from_millis(step.duration).saturating_sub(RELEASE_GAP);
+            self.chain_pause_until = Some(Instant::now() + pause);
+        }
+
+        self.chain_step = Some(step);
+        Ok(())
+    }
+
+    fn chain_step_done(&self, now: Instant) -> bool {
+        self.apply_next.is_none()
+            && now >= self.settled_at
+            && !matches!(self.chain_pause_until, Some(until) if now < until)
+            && self
+                .chain_step
+                .as_ref()
+                .is_some_and(|step| self.packet_can_run(step))
+    }
+
+    /// Releases a movement unless it's still pressed by a packet or hold
+    fn release_if_unused(&mut self, movement: Movement) -> anyhow::Result<()> {
+        if self.movement_deadlines[movement as usize].is_none()
+            && self.hold_deadlines[movement as usize].is_none()
+        {
             self.gamepad.release(movement)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_hold(&mut self, packet: &MovementPacket, hold: Hold) -> anyhow::Result<()> {
+        for movement in packet.movements.iter() {
+            let deadline = &mut self.hold_deadlines[*movement as usize];
+            match hold {
+                Hold::Press => {
+                    info!("Holding {:?} for {}ms", movement, packet.duration);
+                    *deadline = Some(Instant::now() + Duration::from_millis(packet.duration));
+                    self.gamepad.press(*movement)?;
+                }
+                Hold::Release if deadline.is_some() => {
+                    info!("Releasing held {:?}", movement);
+                    *deadline = None;
+                    self.release_if_unused(*movement)?;
+                }
+                Hold::Release => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cancel_if_active(&mut self, movement: Movement) -> anyhow::Result<bool> {
+        if self.movement_deadlines[movement as usize].take().is_some() {
+            self.release_if_unused(movement)?;
+            self.mark_released();
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn cancel_axis_if_active(&mut self, axis: Axis) -> anyhow::Result<bool> {
+        if self.axis_deadlines[axis as usize].take().is_some() {
+            self.gamepad.set_axis(axis, axis.rest_value())?;
+            self.mark_released();
 
             return Ok(true);
         }
@@ -152,6 +366,9 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
         cancelled |= self.cancel_if_active(Movement::Down)?;
         cancelled |= self.cancel_if_active(Movement::Left)?;
         cancelled |= self.cancel_if_active(Movement::Right)?;
+        for axis in Axis::iter().filter(|axis| axis.is_stick()) {
+            cancelled |= self.cancel_axis_if_active(axis)?;
+        }
         Ok(cancelled)
     }
 
@@ -159,18 +376,40 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
         packet
             .movements
             .iter()
-            .all(|movement| self.movement_time_remaining[*movement as usize] == 0)
+            .all(|movement| self.movement_deadlines[*movement as usize].is_none())
+            && packet
+                .axes
+                .iter()
+                .all(|(axis, _)| self.axis_deadlines[*axis as usize].is_none())
+    }
+
+    fn apply_packet(&mut self, packet: &MovementPacket) -> anyhow::Result<()> {
+        let deadline = Instant::now() + Duration::from_millis(packet.duration);
+        for movement in packet.movements.iter() {
+            self.gamepad.press(*movement)?;
+            self.movement_deadlines[*movement as usize] = Some(deadline);
+        }
+
+        for (axis, value) in packet.axes.iter() {
+            self.gamepad.set_axis(*axis, *value)?;
+            self.axis_deadlines[*axis as usize] = Some(deadline);
+        }
+
+        Ok(())
     }
 
     async fn process_packet(
         &mut self,
         packet: &MovementPacket,
-        ticking: bool,
+        from_queue: bool,
     ) -> anyhow::Result<bool> {
-        info!("Processing packet: {:?} ticking: {:?}", packet, ticking);
+        info!(
+            "Processing packet: {:?} from queue: {:?}",
+            packet, from_queue
+        );
 
         if packet.blocking {
-            if self.time_remaining_empty() {
+            if self.deadlines_empty() && self.apply_next.is_none() && !self.chain_active() {
                 blocking_movement(self.gamepad, packet).await?;
                 return Ok(true);
             } else {
@@ -178,7 +417,7 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
             }
         }
 
-        if !ticking && !self.packet_queue.is_empty() {
+        if (!from_queue && !self.packet_queue.is_empty()) || self.chain_active() {
             return Ok(false);
         }
 
@@ -192,18 +431,21 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
                 cancelled |= self.cancel_if_active(*movement)?;
             }
 
+            for (axis, _) in packet.axes.iter() {
+                cancelled |= self.cancel_axis_if_active(*axis)?;
+            }
+
             if cancelled {
-                self.apply_next_tick = Some(packet.clone());
+                self.apply_next = Some(packet.clone());
+                self.start_chain(packet);
                 return Ok(true);
             }
         }
 
         if contains_direction || self.packet_can_run(packet) {
             info!("Executing immediately: {:?}", packet);
-            for movement in packet.movements.iter() {
-                self.gamepad.press(*movement)?;
-                self.movement_time_remaining[*movement as usize] = packet.duration;
-            }
+            self.apply_packet(packet)?;
+            self.start_chain(packet);
 
             return Ok(true);
         }
@@ -220,8 +462,26 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
             }
         };
 
+        if let Some(hold) = packet.hold {
+            return self.process_hold(&packet, hold);
+        }
+
         let processed = self.process_packet(&packet, false).await?;
         if !processed {
+            if self.packet_queue.len() >= MAX_QUEUED_PACKETS {
+                match self.packet_queue.iter().position(|queued| !queued.blocking) {
+                    Some(idx) => {
+                        let dropped = self.packet_queue.remove(idx);
+                        warn!("Queue is full, dropping packet: {:?}", dropped);
+                    }
+                    None if !packet.blocking => {
+                        warn!("Queue is full of blocking packets, dropping: {:?}", packet);
+                        return Ok(());
+                    }
+                    None => {}
+                }
+            }
+
             info!("Queueing packet: {:?}", packet);
             self.packet_queue.push_back(packet);
         }
@@ -229,30 +489,64 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
         Ok(())
     }
 
-    async fn process_tick(&mut self) -> anyhow::Result<bool> {
-        let mut all_zero = true;
+    /// Releases inputs whose deadlines have passed and starts whatever was waiting on them
+    async fn process_deadlines(&mut self) -> anyhow::Result<bool> {
+        let now = Instant::now();
+        let mut released = false;
+        for movement in Movement::iter() {
+            let deadline = &mut self.movement_deadlines[movement as usize];
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                *deadline = None;
+                released = true;
+                self.release_if_unused(movement)?;
+            }
+        }
+
         for movement in Movement::iter() {
-            let time_remaining = &mut self.movement_time_remaining[movement as usize];
-            if *time_remaining == 0 {
-                continue;
+            let deadline = &mut self.hold_deadlines[movement as usize];
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                *deadline = None;
+                info!("Hold on {:?} timed out", movement);
+                self.release_if_unused(movement)?;
             }
+        }
 
-            all_zero = false;
-            *time_remaining = time_remaining.saturating_sub(self.update_interval_ms);
+        for axis in Axis::iter() {
+            let deadline = &mut self.axis_deadlines[axis as usize];
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                *deadline = None;
+                released = true;
+                self.gamepad.set_axis(axis, axis.rest_value())?;
+            }
+        }
 
-            if *time_remaining == 0 {
-                self.gamepad.release(movement)?;
+        if released {
+            self.mark_released();
+        }
+
+        if now >= self.settled_at {
+            if let Some(packet) = self.apply_next.take() {
+                self.apply_packet(&packet)?;
             }
         }
 
-        if let Some(packet) = self.apply_next_tick.take() {
-            for movement in packet.movements {
-                self.gamepad.press(movement)?;
-                self.movement_time_remaining[movement as usize] = packet.duration;
+        if self.chain_step_done(now) {
+            match self.chain.pop_front() {
+                Some(step) if self.packet_can_run(&step) => self.apply_chain_step(&step)?,
+                // Wait for inputs held by packets from before the chain was started
+                Some(step) => self.chain.push_front(step),
+                None => {
+                    self.chain_step = None;
+                    self.chain_pause_until = None;
+                }
             }
         }
 
-        if all_zero {
+        if self.deadlines_empty()
+            && self.apply_next.is_none()
+            && !self.chain_active()
+            && now >= self.settled_at
+        {
             while let Some(packet) = self.packet_queue.pop_front() {
                 if !self.process_packet(&packet, true).await? {
                     info!("Unable to process {:?}, returning to queue", packet);
@@ -262,8 +556,13 @@ impl<'a, G: Gamepad> RunnerState<'a, G> {
             }
         }
 
-        // all_zero is no longer valid here, we may have mutated the remaining time
-        if self.draining && self.time_remaining_empty() && self.packet_queue.is_empty() {
+        if self.draining
+            && self.deadlines_empty()
+            && self.apply_next.is_none()
+            && self.packet_queue.is_empty()
+            && !self.chain_active()
+        {
+            self.process_hold(&MovementPacket::release_all(), Hold::Release)?;
             return Ok(true);
         }
 
@@ -275,27 +574,32 @@ pub async fn gamepad_runner<G: Gamepad>(
     gamepad: &mut G,
     mut rx: Receiver<MovementPacket>,
 ) -> anyhow::Result<()> {
-    let update_interval_ms = 100;
     let mut runner_state = RunnerState {
-        update_interval_ms,
         gamepad,
-        movement_time_remaining: vec![0; Movement::iter().count()].into_boxed_slice(),
-        apply_next_tick: None,
+        movement_deadlines: vec![None; Movement::iter().count()].into_boxed_slice(),
+        axis_deadlines: vec![None; Axis::iter().count()].into_boxed_slice(),
+        hold_deadlines: vec![None; Movement::iter().count()].into_boxed_slice(),
+        apply_next: None,
         packet_queue: VecDeque::new(),
-        interval: tokio::time::interval(tokio::time::Duration::from_millis(update_interval_ms)),
+        chain_step: None,
+        chain: VecDeque::new(),
+        chain_pause_until: None,
+        settled_at: Instant::now(),
         draining: false,
     };
 
     loop {
+        let deadline = runner_state.next_deadline();
         select! {
-            msg = rx.recv() => {
+            msg = rx.recv(), if !runner_state.draining => {
                 runner_state.process_message(msg).await?;
             },
-            _ = runner_state.interval.tick() => {
-                if runner_state.process_tick().await? {
-                    break Ok(());
-                }
-            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {},
+            else => {},
+        }
+
+        if runner_state.process_deadlines().await? {
+            break Ok(());
         }
     }
 }
@@ -1,19 +1,27 @@
 use command::Message;
+use std::collections::HashSet;
 use tokio::{self, io::AsyncBufReadExt};
 use twitch::run_twitch_irc_login;
 
 mod command;
 mod config;
 mod database;
+mod emulator;
+mod game_log;
 mod game_runner;
+mod game_search;
 mod gamepad;
+mod meter;
+mod schedule;
 mod twitch;
+mod vote;
 
 #[cfg(test)]
 mod test;
 
 fn stdin_input(
     tx: tokio::sync::mpsc::Sender<command::WithReply<Message, Option<String>>>,
+    alias_words: HashSet<String>,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     tokio::task::spawn(async move {
         loop {
@@ -25,7 +33,7 @@ fn stdin_input(
                     break;
                 }
 
-                if let Some(cmd) = command::parse_command(&line) {
+                if let Some(cmd) = command::parse_command_with_aliases(&line, &alias_words) {
                     let msg = command::Message {
                         command: cmd,
                         sender_name: "stdin".to_owned(),
@@ -71,11 +79,17 @@ async fn main() {
         None => (None, None),
     };
 
+    let alias_words = config.alias_words();
     let (tx, rx) = tokio::sync::mpsc::channel(100);
+    let (announce_tx, announce_rx) = tokio::sync::mpsc::unbounded_channel();
     let (_, client_handle) = match &config.twitch.auth {
-        config::TwitchAuth::Anonymous => {
-            twitch::run_twitch_irc_anonymous(channel.clone(), tx.clone(), sfx_tx.clone())
-        }
+        config::TwitchAuth::Anonymous => twitch::run_twitch_irc_anonymous(
+            channel.clone(),
+            alias_words.clone(),
+            tx.clone(),
+            sfx_tx.clone(),
+            announce_rx,
+        ),
         config::TwitchAuth::Login {
             client,
             secret,
@@ -107,19 +121,22 @@ async fn main() {
                 secret.clone(),
                 &token_path,
                 channel.clone(),
+                alias_words.clone(),
                 tx.clone(),
                 sfx_tx.clone(),
+                announce_rx,
             )
         }
     };
 
-    stdin_input(tx.clone());
+    stdin_input(tx.clone(), alias_words);
 
     let gamepad = gamepad::UinputGamepad::new().unwrap();
     client_handle.await.unwrap();
 
     let (mut gamepad_handle, gamepad_tx) = gamepad::run_gamepad(gamepad);
-    let (mut game_runner_handle, game_runner_tx) = game_runner::run_game_runner();
+    let (mut game_runner_handle, game_runner_tx, game_event_rx) =
+        game_runner::run_game_runner(config.game_logs().cloned());
 
     let command_runner: tokio::task::JoinHandle<anyhow::Result<()>> =
         tokio::task::spawn(async move {
@@ -127,6 +144,7 @@ async fn main() {
             let config = config;
             let mut db_conn = db_conn;
             let mut game_runner_tx = game_runner_tx;
+            let mut game_event_rx = game_event_rx;
 
             command::run_commands(
                 &mut rx,
@@ -134,7 +152,9 @@ async fn main() {
                 gamepad_tx,
                 &mut db_conn,
                 &mut game_runner_tx,
+                &mut game_event_rx,
                 sfx_tx.as_mut(),
+                announce_tx,
             )
             .await?;
 
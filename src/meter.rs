@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::command::AnarchyType;
+
+pub const METER_MIN: i64 = 0;
+pub const METER_MAX: i64 = 100;
+pub const METER_CENTER: i64 = 50;
+
+/// How far a single vote moves the meter
+const METER_STEP: i64 = 5;
+
+/// The meter must pass these to switch modes, the gap between them keeps it from flapping
+const ANARCHY_THRESHOLD: i64 = 75;
+const DEMOCRACY_THRESHOLD: i64 = 25;
+
+/// Each user can only move the meter once in this interval
+const METER_VOTE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MeterVote {
+    Anarchy,
+    Democracy,
+}
+
+#[derive(Debug)]
+pub struct ModeMeter {
+    /// 0 is full democracy, 100 is full anarchy
+    pub value: i64,
+    pub locked: bool,
+    last_vote: HashMap<String, std::time::Instant>,
+}
+
+impl ModeMeter {
+    pub fn new(value: i64, locked: bool) -> Self {
+        ModeMeter {
+            value: value.clamp(METER_MIN, METER_MAX),
+            locked,
+            last_vote: HashMap::new(),
+        }
+    }
+
+    /// Moves the meter, returns false if the voter has voted too recently
+    pub fn vote(&mut self, voter: &str, vote: MeterVote) -> bool {
+        let now = std::time::Instant::now();
+        if self
+            .last_vote
+            .get(voter)
+            .is_some_and(|last| now < *last + METER_VOTE_INTERVAL)
+        {
+            return false;
+        }
+
+        self.last_vote.insert(voter.to_owned(), now);
+        let step = match vote {
+            MeterVote::Anarchy => METER_STEP,
+            MeterVote::Democracy => -METER_STEP,
+        };
+        self.value = (self.value + step).clamp(METER_MIN, METER_MAX);
+        true
+    }
+
+    /// The mode the meter wants to switch to, if any. Democracy and voting are both on the
+    /// democracy side, so the meter only switches between them and anarchy.
+    pub fn target_mode(&self, current: AnarchyType) -> Option<AnarchyType> {
+        match current {
+            AnarchyType::Anarchy if self.value <= DEMOCRACY_THRESHOLD => Some(AnarchyType::Voting),
+            AnarchyType::Democracy | AnarchyType::Voting if self.value >= ANARCHY_THRESHOLD => {
+                Some(AnarchyType::Anarchy)
+            }
+            _ => None,
+        }
+    }
+
+    /// The meter only runs in modes that chat could have chosen
+    pub fn is_active(current: AnarchyType) -> bool {
+        matches!(
+            current,
+            AnarchyType::Anarchy | AnarchyType::Democracy | AnarchyType::Voting
+        )
+    }
+}
+
+#[cfg(test)]
+mod meter_test {
+    use super::{MeterVote, ModeMeter};
+    use crate::command::AnarchyType;
+
+    #[test]
+    fn votes_move_the_meter() {
+        let mut meter = ModeMeter::new(50, false);
+        assert!(meter.vote("u1", MeterVote::Anarchy));
+        assert!(meter.vote("u2", MeterVote::Anarchy));
+        assert!(meter.vote("u3", MeterVote::Democracy));
+        assert_eq!(meter.value, 55);
+    }
+
+    #[test]
+    fn users_cannot_spam_the_meter() {
+        let mut meter = ModeMeter::new(50, false);
+        assert!(meter.vote("u1", MeterVote::Anarchy));
+        assert!(!meter.vote("u1", MeterVote::Anarchy));
+        assert!(!meter.vote("u1", MeterVote::Democracy));
+        assert_eq!(meter.value, 55);
+    }
+
+    #[test]
+    fn meter_is_clamped() {
+        let mut meter = ModeMeter::new(98, false);
+        meter.vote("u1", MeterVote::Anarchy);
+        assert_eq!(meter.value, 100);
+
+        let meter = ModeMeter::new(-20, false);
+        assert_eq!(meter.value, 0);
+    }
+
+    #[test]
+    fn mode_switches_with_hysteresis() {
+        let meter = ModeMeter::new(50, false);
+        assert_eq!(meter.target_mode(AnarchyType::Anarchy), None);
+        assert_eq!(meter.target_mode(AnarchyType::Voting), None);
+
+        let meter = ModeMeter::new(75, false);
+        assert_eq!(
+            meter.target_mode(AnarchyType::Voting),
+            Some(AnarchyType::Anarchy)
+        );
+        assert_eq!(meter.target_mode(AnarchyType::Anarchy), None);
+
+        // Dropping back below the anarchy threshold isn't enough to leave anarchy
+        let meter = ModeMeter::new(60, false);
+        assert_eq!(meter.target_mode(AnarchyType::Anarchy), None);
+
+        let meter = ModeMeter::new(25, false);
+        assert_eq!(
+            meter.target_mode(AnarchyType::Anarchy),
+            Some(AnarchyType::Voting)
+        );
+        assert_eq!(meter.target_mode(AnarchyType::Voting), None);
+        assert_eq!(meter.target_mode(AnarchyType::Streaming), None);
+    }
+
+    #[test]
+    fn democracy_stays_put_on_the_democracy_side() {
+        for value in [0, 25, 50] {
+            let meter = ModeMeter::new(value, false);
+            assert_eq!(meter.target_mode(AnarchyType::Democracy), None);
+        }
+
+        let meter = ModeMeter::new(80, false);
+        assert_eq!(
+            meter.target_mode(AnarchyType::Democracy),
+            Some(AnarchyType::Anarchy)
+        );
+    }
+}
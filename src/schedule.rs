@@ -0,0 +1,251 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{de::Error, Deserialize, Deserializer};
+
+use crate::{command::AnarchyType, config::GameName};
+
+/// Slots are looked up this many days ahead when showing what's next
+const LOOKAHEAD_DAYS: i64 = 8;
+
+const KEY_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+fn deserialize_time<'d, D: Deserializer<'d>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let time = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&time, "%H:%M")
+        .map_err(|e| D::Error::custom(format!("invalid time {}: {}", time, e)))
+}
+
+fn deserialize_days<'d, D: Deserializer<'d>>(deserializer: D) -> Result<Vec<Weekday>, D::Error> {
+    let days: Vec<String> = Vec::deserialize(deserializer)?;
+    days.iter()
+        .map(|day| {
+            day.parse::<Weekday>()
+                .map_err(|_| D::Error::custom(format!("invalid day {}", day)))
+        })
+        .collect()
+}
+
+fn deserialize_mode<'d, D: Deserializer<'d>>(
+    deserializer: D,
+) -> Result<Option<AnarchyType>, D::Error> {
+    let mode = String::deserialize(deserializer)?;
+    AnarchyType::from_str(&mode)
+        .map(Some)
+        .ok_or_else(|| D::Error::custom(format!("invalid mode {}", mode)))
+}
+
+/// A time slot that starts a game and/or changes the mode when it begins
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScheduleSlot {
+    /// Shown in chat, the slot's game and mode by default
+    pub name: Option<String>,
+    /// Days the slot starts on, every day by default
+    #[serde(default, deserialize_with = "deserialize_days")]
+    pub days: Vec<Weekday>,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime,
+    /// A slot ending at or before its start runs past midnight
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: NaiveTime,
+    /// Started when the slot begins and stopped when it ends
+    pub game: Option<GameName>,
+    /// Set when the slot begins and left as is when it ends
+    #[serde(default, deserialize_with = "deserialize_mode")]
+    pub mode: Option<AnarchyType>,
+}
+
+/// A single run of a slot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotTime {
+    /// Index of the slot in the schedule
+    pub slot: usize,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl ScheduleSlot {
+    /// The run of this slot starting on a date, if it runs that day
+    fn on(&self, slot: usize, date: NaiveDate) -> Option<SlotTime> {
+        if !self.days.is_empty() && !self.days.contains(&date.weekday()) {
+            return None;
+        }
+
+        let start = date.and_time(self.start);
+        let mut end = date.and_time(self.end);
+        if end <= start {
+            end += chrono::Duration::days(1);
+        }
+
+        Some(SlotTime { slot, start, end })
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(ref name) = self.name {
+            return name.clone();
+        }
+
+        match (&self.game, self.mode) {
+            (Some(game), Some(mode)) => format!("{} in {} mode", game, mode.to_str()),
+            (Some(game), None) => game.clone(),
+            (None, Some(mode)) => format!("{} mode", mode.to_str()),
+            (None, None) => "Nothing".to_owned(),
+        }
+    }
+}
+
+impl SlotTime {
+    /// Identifies the run across restarts
+    pub fn key(&self) -> String {
+        format!("{}@{}", self.slot, self.start.format(KEY_TIME_FORMAT))
+    }
+
+    pub fn from_key(slots: &[ScheduleSlot], key: &str) -> Option<Self> {
+        let (slot, start) = key.split_once('@')?;
+        let slot: usize = slot.parse().ok()?;
+        let start = NaiveDateTime::parse_from_str(start, KEY_TIME_FORMAT).ok()?;
+        slots
+            .get(slot)?
+            .on(slot, start.date())
+            .filter(|run| run.start == start)
+    }
+}
+
+/// Runs of every slot starting from the day before `from`, earliest first
+fn runs(slots: &[ScheduleSlot], from: NaiveDateTime) -> Vec<SlotTime> {
+    let mut runs: Vec<SlotTime> = (-1..LOOKAHEAD_DAYS)
+        .map(|offset| from.date() + chrono::Duration::days(offset))
+        .flat_map(|date| {
+            slots
+                .iter()
+                .enumerate()
+                .filter_map(move |(idx, slot)| slot.on(idx, date))
+        })
+        .collect();
+
+    runs.sort_by_key(|run| (run.start, run.slot));
+    runs
+}
+
+/// The slot running at a time. When slots overlap the one that started last wins.
+pub fn active_at(slots: &[ScheduleSlot], at: NaiveDateTime) -> Option<SlotTime> {
+    runs(slots, at)
+        .into_iter()
+        .rev()
+        .find(|run| run.start <= at && at < run.end)
+}
+
+/// Runs starting after a time, earliest first
+pub fn upcoming(slots: &[ScheduleSlot], after: NaiveDateTime, count: usize) -> Vec<SlotTime> {
+    runs(slots, after)
+        .into_iter()
+        .filter(|run| run.start > after)
+        .take(count)
+        .collect()
+}
+
+/// The next time a slot starts or ends
+pub fn next_change(slots: &[ScheduleSlot], after: NaiveDateTime) -> Option<NaiveDateTime> {
+    runs(slots, after)
+        .into_iter()
+        .flat_map(|run| [run.start, run.end])
+        .filter(|time| *time > after)
+        .min()
+}
+
+#[cfg(test)]
+mod schedule_test {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::{active_at, next_change, upcoming, ScheduleSlot, SlotTime};
+
+    #[derive(serde::Deserialize)]
+    struct TestSchedule {
+        schedule: Vec<ScheduleSlot>,
+    }
+
+    fn schedule() -> Vec<ScheduleSlot> {
+        let schedule: TestSchedule = toml::from_str(
+            r#"
+            [[schedule]]
+            start = "18:00"
+            end = "22:00"
+            game = "Game X"
+
+            [[schedule]]
+            start = "23:00"
+            end = "07:00"
+            mode = "streaming"
+
+            [[schedule]]
+            name = "Anarchy weekend"
+            days = ["sat", "Sunday"]
+            start = "00:00"
+            end = "00:00"
+            mode = "anarchy"
+            "#,
+        )
+        .unwrap();
+        schedule.schedule
+    }
+
+    /// 2024-06-07 is a Friday
+    fn at(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn slots_are_active_between_start_and_end() {
+        let schedule = schedule();
+        assert_eq!(active_at(&schedule, at(7, 12, 0)), None);
+
+        let run = active_at(&schedule, at(7, 18, 0)).unwrap();
+        assert_eq!(run.slot, 0);
+        assert_eq!(run.end, at(7, 22, 0));
+        assert_eq!(active_at(&schedule, at(7, 22, 0)), None);
+
+        // Overnight slots run into the next day
+        let run = active_at(&schedule, at(7, 6, 59)).unwrap();
+        assert_eq!(run.slot, 1);
+        assert_eq!(run.start, at(6, 23, 0));
+    }
+
+    #[test]
+    fn later_slots_win_when_overlapping() {
+        let schedule = schedule();
+        // Streaming started Friday night, the weekend started at midnight
+        assert_eq!(active_at(&schedule, at(8, 1, 0)).unwrap().slot, 2);
+        assert_eq!(active_at(&schedule, at(8, 19, 0)).unwrap().slot, 0);
+        assert_eq!(active_at(&schedule, at(8, 23, 30)).unwrap().slot, 1);
+        assert_eq!(active_at(&schedule, at(10, 12, 0)), None);
+        assert_eq!(schedule[2].describe(), "Anarchy weekend");
+        assert_eq!(schedule[1].describe(), "streaming mode");
+    }
+
+    #[test]
+    fn next_changes_are_found() {
+        let schedule = schedule();
+        assert_eq!(next_change(&schedule, at(7, 12, 0)), Some(at(7, 18, 0)));
+        assert_eq!(next_change(&schedule, at(7, 18, 0)), Some(at(7, 22, 0)));
+        assert_eq!(next_change(&schedule, at(7, 23, 30)), Some(at(8, 0, 0)));
+
+        let next: Vec<usize> = upcoming(&schedule, at(7, 20, 0), 3)
+            .iter()
+            .map(|run| run.slot)
+            .collect();
+        assert_eq!(next, vec![1, 2, 0]);
+        assert_eq!(next_change(&[], at(7, 12, 0)), None);
+    }
+
+    #[test]
+    fn runs_are_found_by_key() {
+        let schedule = schedule();
+        let run = active_at(&schedule, at(8, 3, 0)).unwrap();
+        assert_eq!(run.key(), "2@2024-06-08T00:00");
+        assert_eq!(SlotTime::from_key(&schedule, &run.key()), Some(run));
+        assert_eq!(SlotTime::from_key(&schedule, "2@2024-06-07T00:00"), None);
+        assert_eq!(SlotTime::from_key(&schedule, "5@2024-06-08T00:00"), None);
+        assert_eq!(SlotTime::from_key(&schedule, "garbage"), None);
+    }
+}
@@ -3,11 +3,19 @@ use std::collections::BTreeMap;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    command::{self, AnarchyType, Command, Message, Movement, MovementPacket, Privilege},
-    config::{Config, GameCommandString, GameInfo, GameName},
+    command::{
+        self, AnarchyType, Axis, Command, Message, Movement, MovementPacket, Privilege, SaveSlot,
+    },
+    config::{
+        Config, GameCommandString, GameInfo, GameName, HoldConfig, HotkeyConfig, HotkeysConfig,
+        LoadingInput, SaveSlotConfig,
+    },
     database,
-    game_runner::{GameRunner, SfxRequest},
+    emulator::{EmulatorAction, EmulatorConfig},
+    game_log::GameLogConfig,
+    game_runner::{GameEvent, GameRunner, ReadyCheck, SfxRequest},
     gamepad::Gamepad,
+    schedule::ScheduleSlot,
 };
 
 #[derive(Eq, PartialEq, Debug)]
@@ -19,6 +27,7 @@ enum ActionType {
 #[derive(Default, Debug)]
 struct DummyGamepad {
     actions: std::collections::LinkedList<(crate::command::Movement, ActionType)>,
+    axis_actions: std::collections::LinkedList<(crate::command::Axis, i32)>,
 }
 
 impl Gamepad for DummyGamepad {
@@ -31,6 +40,11 @@ impl Gamepad for DummyGamepad {
         self.actions.push_back((movement, ActionType::Release));
         Ok(())
     }
+
+    fn set_axis(&mut self, axis: crate::command::Axis, value: i32) -> anyhow::Result<()> {
+        self.axis_actions.push_back((axis, value));
+        Ok(())
+    }
 }
 
 impl DummyGamepad {
@@ -46,6 +60,16 @@ impl DummyGamepad {
             assert_eq!(actual_type, expected_type);
         }
     }
+
+    fn expect_axis_sequence(&self, seq: &[(crate::command::Axis, i32)]) {
+        eprintln!("expected: {:?}", seq);
+        eprintln!("actual: {:?}", self.axis_actions);
+
+        assert_eq!(seq.len(), self.axis_actions.len());
+        for (actual, expected) in self.axis_actions.iter().zip(seq.iter()) {
+            assert_eq!(actual, expected);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -55,6 +79,14 @@ struct TestSetup {
     gamepad: DummyGamepad,
     game_runner_cmds: Vec<GameRunner>,
     sfx_cmds: Vec<SfxRequest>,
+    announcements: Vec<String>,
+    hotkeys: Option<HotkeysConfig>,
+    emulator: Option<EmulatorConfig>,
+    resume_autosave_delay: Option<std::time::Duration>,
+    game_logs: Option<GameLogConfig>,
+    schedule: Option<Vec<ScheduleSlot>>,
+    game_event_tx: tokio::sync::mpsc::UnboundedSender<GameEvent>,
+    game_event_rx: tokio::sync::mpsc::UnboundedReceiver<GameEvent>,
 }
 
 impl TestSetup {
@@ -67,6 +99,7 @@ impl TestSetup {
 
         let gamepad = DummyGamepad::default();
         let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let (game_event_tx, game_event_rx) = tokio::sync::mpsc::unbounded_channel();
 
         (
             TestSetup {
@@ -75,6 +108,14 @@ impl TestSetup {
                 gamepad,
                 game_runner_cmds: vec![],
                 sfx_cmds: vec![],
+                announcements: vec![],
+                hotkeys: Some(retroarch_hotkeys()),
+                emulator: None,
+                resume_autosave_delay: None,
+                game_logs: None,
+                schedule: None,
+                game_event_tx,
+                game_event_rx,
             },
             tx,
         )
@@ -95,6 +136,17 @@ impl TestSetup {
             },
             sound_effects: None,
             games,
+            rom_dirs: None,
+            hotkeys: self.hotkeys.clone(),
+            emulator: self.emulator.clone(),
+            save_slots: None,
+            holds: None,
+            resume_autosave_delay: self.resume_autosave_delay,
+            game_logs: self.game_logs.clone(),
+            stop_timeout: None,
+            schedule: self.schedule.clone(),
+            mash_rate: None,
+            aliases: None,
         };
 
         let (mut game_runner_tx, mut rx) = tokio::sync::mpsc::channel(10);
@@ -117,6 +169,16 @@ impl TestSetup {
             sfx_cmds
         });
 
+        let (announce_tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let announce_jh = tokio::task::spawn(async move {
+            let mut announcements = Vec::new();
+            while let Some(announcement) = rx.recv().await {
+                announcements.push(announcement);
+            }
+
+            announcements
+        });
+
         let gamepad = DummyGamepad::default();
         let (gamepad_jh, gamepad_tx) = crate::gamepad::run_gamepad(gamepad);
 
@@ -126,7 +188,9 @@ impl TestSetup {
             gamepad_tx,
             &mut self.db_conn,
             &mut game_runner_tx,
+            &mut self.game_event_rx,
             Some(&mut sfx_tx),
+            announce_tx,
         )
         .await
         .unwrap();
@@ -141,6 +205,9 @@ impl TestSetup {
 
         let mut sfx_cmds = sfx_runner_jh.await.unwrap();
         self.sfx_cmds.append(&mut sfx_cmds);
+
+        let mut announcements = announce_jh.await.unwrap();
+        self.announcements.append(&mut announcements);
         Ok(())
     }
 }
@@ -154,15 +221,37 @@ async fn send_message(
     rx.await.unwrap()
 }
 
+fn hotkey(buttons: &[&str]) -> Option<HotkeyConfig> {
+    Some(HotkeyConfig {
+        buttons: buttons.iter().map(|b| b.to_string()).collect(),
+        stagger_ms: None,
+        hold_ms: None,
+    })
+}
+
+fn retroarch_hotkeys() -> HotkeysConfig {
+    HotkeysConfig {
+        save_state: hotkey(&["mode", "a"]),
+        load_state: hotkey(&["mode", "b"]),
+        reset: hotkey(&["mode", "x"]),
+        ..Default::default()
+    }
+}
+
 fn single_movement(movement: Movement) -> Command {
     let movements = vec![movement];
     Command::Movement(MovementPacket {
         movements,
+        axes: vec![],
         duration: 50,
         stagger: 0,
 
         // Don't allow interruption so tests are deterministic
         blocking: true,
+        chain: vec![],
+        repeat: None,
+        hold: None,
+        frames: None,
     })
 }
 
@@ -179,9 +268,14 @@ async fn can_send_multiple_movements() {
             Message {
                 command: Command::Movement(MovementPacket {
                     movements,
+                    axes: vec![],
                     duration: 50,
                     stagger: 0,
                     blocking: true,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
                 }),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
@@ -1079,8 +1173,7 @@ async fn can_list_games() {
         name,
         GameInfo {
             command: GameCommandString("cmdforgame1 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
+            ..Default::default()
         },
     );
 
@@ -1089,8 +1182,7 @@ async fn can_list_games() {
         name,
         GameInfo {
             command: GameCommandString("cmdforgame2 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
+            ..Default::default()
         },
     );
 
@@ -1098,7 +1190,7 @@ async fn can_list_games() {
         let response = send_message(
             &mut tx,
             Message {
-                command: Command::ListGames,
+                command: Command::ListGames(None),
                 sender_id: broadcaster_id.clone(),
                 sender_name: broadcaster_name.clone(),
                 privilege: Privilege::Standard,
@@ -1130,7 +1222,7 @@ async fn operator_can_save_state() {
         send_message(
             &mut tx,
             Message {
-                command: Command::SaveState,
+                command: Command::Emulator(EmulatorAction::SaveState),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1162,7 +1254,7 @@ async fn operator_can_load_state() {
         send_message(
             &mut tx,
             Message {
-                command: Command::LoadState,
+                command: Command::Emulator(EmulatorAction::LoadState),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1193,7 +1285,7 @@ async fn user_cannot_save_state() {
         send_message(
             &mut tx,
             Message {
-                command: Command::SaveState,
+                command: Command::Emulator(EmulatorAction::SaveState),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1219,7 +1311,7 @@ async fn user_cannot_load_state() {
         send_message(
             &mut tx,
             Message {
-                command: Command::LoadState,
+                command: Command::Emulator(EmulatorAction::LoadState),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1246,7 +1338,7 @@ async fn operator_can_reset_game() {
         send_message(
             &mut tx,
             Message {
-                command: Command::Reset,
+                command: Command::Emulator(EmulatorAction::Reset),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1277,7 +1369,7 @@ async fn user_cannot_reset_game() {
         send_message(
             &mut tx,
             Message {
-                command: Command::Reset,
+                command: Command::Emulator(EmulatorAction::Reset),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Standard,
@@ -1292,31 +1384,21 @@ async fn user_cannot_reset_game() {
 }
 
 #[tokio::test]
-async fn moderator_can_switch_games() {
+async fn game_hotkeys_override_global_hotkeys() {
     let (mut test, mut tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
     let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
-
-    let name: GameName = "Game 1".to_owned();
     games.insert(
-        name,
+        "Game 1".to_owned(),
         GameInfo {
             command: GameCommandString("cmdforgame1 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
-
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd.clone(),
-            restricted_inputs: None,
-            controls: None,
+            hotkeys: Some(HotkeysConfig {
+                save_state: hotkey(&["select", "start"]),
+                ..Default::default()
+            }),
+            ..Default::default()
         },
     );
 
@@ -1324,659 +1406,962 @@ async fn moderator_can_switch_games() {
         send_message(
             &mut tx,
             Message {
-                command: Command::Game("Game 2".to_owned()),
+                command: Command::Game("Game 1".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+
+        for command in [
+            Command::Emulator(EmulatorAction::SaveState),
+            Command::Emulator(EmulatorAction::LoadState),
+        ] {
+            send_message(
+                &mut tx,
+                Message {
+                    command,
+                    sender_id: user_id.clone(),
+                    sender_name: user_name.clone(),
+                    privilege: Privilege::Moderator,
+                },
+            )
+            .await;
+        }
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
-
-    assert_eq!(test.game_runner_cmds.len(), 1);
-    assert_eq!(
-        test.game_runner_cmds[0],
-        GameRunner::SwitchTo(game2_cmd.to_command())
-    );
+    test.gamepad.expect_sequence(&[
+        (Movement::Select, ActionType::Press),
+        (Movement::Start, ActionType::Press),
+        (Movement::Start, ActionType::Release),
+        (Movement::Select, ActionType::Release),
+        (Movement::Mode, ActionType::Press),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+        (Movement::Mode, ActionType::Release),
+    ]);
 }
 
 #[tokio::test]
-async fn moderator_can_stop_gameplay() {
+async fn unconfigured_hotkeys_are_unsupported() {
     let (mut test, mut tx) = TestSetup::new();
+    test.hotkeys = None;
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
-    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
-
-    let name: GameName = "Game 1".to_owned();
-    games.insert(
-        name,
-        GameInfo {
-            command: GameCommandString("cmdforgame1 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
-
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd,
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
-
     let join_handle = tokio::task::spawn(async move {
-        send_message(
+        let reply = send_message(
             &mut tx,
             Message {
-                command: Command::Stop,
+                command: Command::Emulator(EmulatorAction::Reset),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+
+        assert_eq!(
+            reply,
+            Some("Resetting is not supported for this game".to_owned())
+        );
     });
 
-    test.run_with_games(Some(games)).await.unwrap();
+    test.run().await.unwrap();
     join_handle.await.unwrap();
-
-    assert_eq!(test.game_runner_cmds.len(), 1);
-    assert_eq!(test.game_runner_cmds[0], GameRunner::Stop);
+    test.gamepad.expect_sequence(&[]);
 }
 
 #[tokio::test]
-async fn user_cannot_switch_games() {
+async fn retroarch_backend_sends_network_commands() {
     let (mut test, mut tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
-    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    test.emulator = Some(EmulatorConfig::RetroArch {
+        address: Some(listener.local_addr().unwrap()),
+    });
 
-    let name: GameName = "Game 1".to_owned();
-    games.insert(
-        name,
-        GameInfo {
-            command: GameCommandString("cmdforgame1 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
+    let join_handle = tokio::task::spawn(async move {
+        for action in [EmulatorAction::SaveState, EmulatorAction::Pause] {
+            let reply = send_message(
+                &mut tx,
+                Message {
+                    command: Command::Emulator(action),
+                    sender_id: user_id.clone(),
+                    sender_name: user_name.clone(),
+                    privilege: Privilege::Operator,
+                },
+            )
+            .await;
+
+            assert_eq!(reply, Some(action.done_msg().to_owned()));
+        }
+    });
 
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd,
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+
+    // Nothing should be pressed on the gamepad
+    test.gamepad.expect_sequence(&[]);
+
+    let mut buf = [0u8; 64];
+    for expected in ["SAVE_STATE", "PAUSE_TOGGLE"] {
+        let len = listener.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], expected.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn retroarch_failures_are_reported() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    // Grab a free port and close it so that nothing is listening
+    let address = tokio::net::UdpSocket::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    test.emulator = Some(EmulatorConfig::RetroArch {
+        address: Some(address),
+    });
 
     let join_handle = tokio::task::spawn(async move {
-        send_message(
+        let reply = send_message(
             &mut tx,
             Message {
-                command: Command::Game("Game 2".to_owned()),
+                command: Command::Emulator(EmulatorAction::LoadState),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Standard,
+                privilege: Privilege::Operator,
             },
         )
         .await;
+
+        let reply = reply.unwrap();
+        assert!(reply.starts_with("Failed to load state"), "{}", reply);
     });
 
-    test.run_with_games(Some(games)).await.unwrap();
+    test.run().await.unwrap();
     join_handle.await.unwrap();
-
-    assert_eq!(test.game_runner_cmds.len(), 0);
 }
 
-#[tokio::test]
-async fn user_cannot_stop_gameplay() {
-    let (mut test, mut tx) = TestSetup::new();
-    let user_id = "user_id".to_owned();
-    let user_name = "user_name".to_owned();
-
+fn retroarch_game(listener: &tokio::net::UdpSocket) -> BTreeMap<GameName, GameInfo> {
     let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
-
-    let name: GameName = "Game 1".to_owned();
     games.insert(
-        name,
+        "Game 1".to_owned(),
         GameInfo {
             command: GameCommandString("cmdforgame1 --command".to_owned()),
-            restricted_inputs: None,
-            controls: None,
+            emulator: Some(EmulatorConfig::RetroArch {
+                address: Some(listener.local_addr().unwrap()),
+            }),
+            ..Default::default()
         },
     );
+    games
+}
 
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd,
-            restricted_inputs: None,
-            controls: None,
-        },
-    );
+async fn expect_retroarch_commands(listener: &tokio::net::UdpSocket, expected: &[&str]) {
+    let mut buf = [0u8; 64];
+    for expected in expected {
+        let len = listener.recv(&mut buf).await.unwrap();
+        assert_eq!(std::str::from_utf8(&buf[..len]).unwrap(), *expected);
+    }
+}
+
+#[tokio::test]
+async fn named_save_slots_are_tracked() {
+    let (mut test, tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
 
     let join_handle = tokio::task::spawn(async move {
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Stop,
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Standard,
-            },
-        )
-        .await;
+                privilege: Privilege::Moderator,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send(Command::Game("Game 1".to_owned())).await;
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Name("boss-fight".to_owned()))).await,
+            Some("Saved game state to slot 1 (boss-fight)".to_owned())
+        );
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(3))).await,
+            Some("Saved game state to slot 3".to_owned())
+        );
+        assert_eq!(
+            send(Command::LoadSlot(SaveSlot::Name("boss-fight".to_owned()))).await,
+            Some("Loaded game state from slot 1 (boss-fight)".to_owned())
+        );
+        assert_eq!(
+            send(Command::LoadSlot(SaveSlot::Name("missing".to_owned()))).await,
+            Some("No save named missing, see \"tp slots\"".to_owned())
+        );
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(10))).await,
+            Some("Save slots are numbered 0 to 9".to_owned())
+        );
+
+        let slots = send(Command::ListSlots).await.unwrap();
+        assert!(slots.starts_with("Save slots for Game 1: 1 boss-fight ("));
+        assert!(slots.contains(", 3 ("));
+        drop(tx);
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    assert_eq!(test.game_runner_cmds.len(), 0);
+    expect_retroarch_commands(
+        &listener,
+        &[
+            "STATE_SLOT_PLUS",
+            "SAVE_STATE",
+            "STATE_SLOT_PLUS",
+            "STATE_SLOT_PLUS",
+            "SAVE_STATE",
+            "STATE_SLOT_MINUS",
+            "STATE_SLOT_MINUS",
+            "LOAD_STATE",
+        ],
+    )
+    .await;
+
+    let slots = database::list_save_slots(&test.db_conn, "Game 1").unwrap();
+    assert_eq!(slots.len(), 2);
+    assert_eq!(slots[0].name, Some("boss-fight".to_owned()));
+    assert_eq!(slots[1].saved_by, "user_name");
 }
 
 #[tokio::test]
-async fn restricted_inputs_are_blocked_in_normal_modes() {
-    let (mut test, mut tx) = TestSetup::new();
+async fn slot_overwrite_privilege_is_enforced() {
+    let (mut test, tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
-    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
-
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd.clone(),
-            restricted_inputs: Some(vec!["start".to_owned()]),
-            controls: None,
-        },
-    );
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut games = retroarch_game(&listener);
+    games.get_mut("Game 1").unwrap().save_slots = Some(SaveSlotConfig {
+        overwrite_privilege: BTreeMap::from([(0, Privilege::Moderator)]),
+        ..Default::default()
+    });
 
     let join_handle = tokio::task::spawn(async move {
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Game("Game 2".to_owned()),
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Moderator,
-            },
-        )
-        .await;
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
 
-        let movements = vec![Movement::Start, Movement::B];
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: true,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Moderator,
-            },
-        )
-        .await;
+        send(Command::Game("Game 1".to_owned()), Privilege::Moderator).await;
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(0)), Privilege::Operator).await,
+            Some("Saved game state to slot 0".to_owned())
+        );
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(0)), Privilege::Operator).await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(0)), Privilege::Moderator).await,
+            Some("Saved game state to slot 0".to_owned())
+        );
+        assert_eq!(
+            send(Command::LoadSlot(SaveSlot::Number(0)), Privilege::Standard).await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        drop(tx);
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    assert_eq!(test.game_runner_cmds.len(), 1);
-    assert_eq!(
-        test.game_runner_cmds[0],
-        GameRunner::SwitchTo(game2_cmd.to_command())
-    );
-    test.gamepad.expect_sequence(&[]);
+    expect_retroarch_commands(&listener, &["SAVE_STATE", "SAVE_STATE"]).await;
 }
 
 #[tokio::test]
-async fn restricted_inputs_are_not_blocked_in_restricted_mode() {
-    let (mut test, mut tx) = TestSetup::new();
+async fn selected_slot_follows_slot_rules() {
+    let (mut test, tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
-    let op_id = "op_id".to_owned();
-    let op_name = "op_name".to_owned();
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut games = retroarch_game(&listener);
+    games.get_mut("Game 1").unwrap().save_slots = Some(SaveSlotConfig {
+        count: 2,
+        overwrite_privilege: BTreeMap::from([(1, Privilege::Moderator)]),
+        ..Default::default()
+    });
 
-    database::update_user(&test.db_conn, &op_id, &op_name).unwrap();
-    database::op_user(&mut test.db_conn, &op_name).unwrap();
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
 
-    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+        send(Command::Game("Game 1".to_owned()), Privilege::Moderator).await;
+        assert_eq!(
+            send(
+                Command::Emulator(EmulatorAction::StateSlotMinus),
+                Privilege::Operator
+            )
+            .await,
+            Some("Save slots are numbered 0 to 1".to_owned())
+        );
+        assert_eq!(
+            send(Command::SaveSlot(SaveSlot::Number(1)), Privilege::Moderator).await,
+            Some("Saved game state to slot 1".to_owned())
+        );
+        assert_eq!(
+            send(
+                Command::Emulator(EmulatorAction::SaveState),
+                Privilege::Operator
+            )
+            .await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        assert_eq!(
+            send(
+                Command::Emulator(EmulatorAction::StateSlotPlus),
+                Privilege::Operator
+            )
+            .await,
+            Some("Save slots are numbered 0 to 1".to_owned())
+        );
+        assert_eq!(
+            send(
+                Command::Emulator(EmulatorAction::StateSlotMinus),
+                Privilege::Operator
+            )
+            .await,
+            Some("Selected state slot 0".to_owned())
+        );
+        assert_eq!(
+            send(
+                Command::Emulator(EmulatorAction::SaveState),
+                Privilege::Operator
+            )
+            .await,
+            Some("Saved game state to slot 0".to_owned())
+        );
+        drop(tx);
+    });
 
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd.clone(),
-            restricted_inputs: Some(vec!["start".to_owned()]),
-            controls: None,
-        },
-    );
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    expect_retroarch_commands(
+        &listener,
+        &[
+            "STATE_SLOT_PLUS",
+            "SAVE_STATE",
+            "STATE_SLOT_MINUS",
+            "SAVE_STATE",
+        ],
+    )
+    .await;
+
+    let slots = database::list_save_slots(&test.db_conn, "Game 1").unwrap();
+    assert_eq!(slots.len(), 2);
+}
+
+#[tokio::test]
+async fn games_are_autosaved_on_interval() {
+    let (mut test, tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut games = retroarch_game(&listener);
+    games.get_mut("Game 1").unwrap().autosave_interval =
+        Some(std::time::Duration::from_millis(200));
 
     let join_handle = tokio::task::spawn(async move {
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Game("Game 2".to_owned()),
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
-            },
-        )
-        .await;
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
 
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::SetAnarchyMode(AnarchyType::Restricted),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Moderator,
-            },
-        )
-        .await;
-
-        let movements = vec![Movement::Start, Movement::B];
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: true,
-                }),
-                sender_id: op_id.clone(),
-                sender_name: op_name.clone(),
-                privilege: Privilege::Standard,
-            },
-        )
-        .await;
+        assert_eq!(
+            send(Command::PrintAutosave).await,
+            Some("No game is running".to_owned())
+        );
+
+        send(Command::Game("Game 1".to_owned())).await;
+        assert_eq!(
+            send(Command::PrintAutosave).await,
+            Some("No auto-saves yet for Game 1, saving every 0.2 seconds".to_owned())
+        );
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        let reply = send(Command::PrintAutosave).await.unwrap();
+        assert!(
+            reply.starts_with("Last auto-save for Game 1 was at"),
+            "{}",
+            reply
+        );
+        assert!(reply.contains("in slot 1"), "{}", reply);
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    assert_eq!(test.game_runner_cmds.len(), 1);
-    assert_eq!(
-        test.game_runner_cmds[0],
-        GameRunner::SwitchTo(game2_cmd.to_command())
-    );
-    test.gamepad.expect_sequence(&[
-        (Movement::Start, ActionType::Press),
-        (Movement::B, ActionType::Press),
-        (Movement::B, ActionType::Release),
-        (Movement::Start, ActionType::Release),
-    ]);
+    expect_retroarch_commands(&listener, &["STATE_SLOT_PLUS", "SAVE_STATE"]).await;
 }
 
 #[tokio::test]
-async fn users_cannot_send_input_in_restricted_mode() {
+async fn games_are_autosaved_before_stopping() {
     let (mut test, mut tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
-    let mod_id = "mod_id".to_owned();
-    let mod_name = "mod_name".to_owned();
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut games = retroarch_game(&listener);
+    games.get_mut("Game 1").unwrap().autosave_interval = Some(std::time::Duration::from_secs(3600));
 
-    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    let join_handle = tokio::task::spawn(async move {
+        for command in [Command::Game("Game 1".to_owned()), Command::Stop] {
+            send_message(
+                &mut tx,
+                Message {
+                    command,
+                    sender_id: user_id.clone(),
+                    sender_name: user_name.clone(),
+                    privilege: Privilege::Moderator,
+                },
+            )
+            .await;
+        }
+    });
 
-    let name: GameName = "Game 2".to_owned();
-    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
-    games.insert(
-        name,
-        GameInfo {
-            command: game2_cmd.clone(),
-            restricted_inputs: Some(vec!["start".to_owned()]),
-            controls: None,
-        },
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    expect_retroarch_commands(&listener, &["STATE_SLOT_PLUS", "SAVE_STATE"]).await;
+    assert_eq!(test.game_runner_cmds.len(), 3);
+    assert_eq!(
+        test.game_runner_cmds[1],
+        GameRunner::DelayStop(std::time::Duration::from_secs(2))
     );
+    assert_eq!(test.game_runner_cmds[2], GameRunner::Stop);
+
+    let slots = database::list_save_slots(&test.db_conn, "Game 1").unwrap();
+    assert_eq!(slots.len(), 1);
+    assert_eq!(slots[0].name.as_deref(), Some("autosave"));
+}
+
+#[tokio::test]
+async fn current_game_is_persisted() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
 
     let join_handle = tokio::task::spawn(async move {
         send_message(
             &mut tx,
             Message {
-                command: Command::Game("Game 2".to_owned()),
-                sender_id: mod_id.clone(),
-                sender_name: mod_name.clone(),
+                command: Command::Game("Game 1".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+    });
+
+    test.run_with_games(Some(games.clone())).await.unwrap();
+    join_handle.await.unwrap();
+    assert_eq!(
+        database::get_kv::<Option<String>>(&test.db_conn, "current_game")
+            .unwrap()
+            .flatten(),
+        Some("Game 1".to_owned())
+    );
 
+    // Restart with the same database
+    let (mut tx, rx) = tokio::sync::mpsc::channel(10);
+    test.msg_rx = rx;
+    let join_handle = tokio::task::spawn(async move {
         send_message(
             &mut tx,
             Message {
-                command: Command::SetAnarchyMode(AnarchyType::Restricted),
-                sender_id: mod_id.clone(),
-                sender_name: mod_name.clone(),
+                command: Command::Stop,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+    });
 
-        send_message(
-            &mut tx,
-            Message {
-                command: single_movement(Movement::A),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Standard,
-            },
-        )
-        .await;
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+    assert_eq!(
+        database::get_kv::<Option<String>>(&test.db_conn, "current_game")
+            .unwrap()
+            .flatten(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn previous_game_is_resumed_at_startup() {
+    let (mut test, tx) = TestSetup::new();
+    test.resume_autosave_delay = Some(std::time::Duration::from_millis(50));
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
+
+    database::set_kv(&test.db_conn, "current_game", "Game 1").unwrap();
+    database::record_save_slot(&test.db_conn, "Game 1", 2, Some("autosave"), "autosave").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        drop(tx);
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    assert_eq!(test.game_runner_cmds.len(), 1);
     assert_eq!(
-        test.game_runner_cmds[0],
-        GameRunner::SwitchTo(game2_cmd.to_command())
+        test.game_runner_cmds,
+        vec![GameRunner::SwitchTo(Box::new(
+            GameCommandString("cmdforgame1 --command".to_owned()).to_command("Game 1")
+        ))]
     );
-    test.gamepad.expect_sequence(&[]);
+    expect_retroarch_commands(
+        &listener,
+        &["STATE_SLOT_PLUS", "STATE_SLOT_PLUS", "LOAD_STATE"],
+    )
+    .await;
 }
 
 #[tokio::test]
-async fn can_interrupt_movements_with_direction() {
+async fn previous_game_is_resumed_once_ready() {
+    let (mut test, tx) = TestSetup::new();
+    test.resume_autosave_delay = Some(std::time::Duration::from_millis(150));
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut games = retroarch_game(&listener);
+    let game = games.get_mut("Game 1").unwrap();
+    game.ready = Some(ReadyCheck::Output {
+        contains: "loaded".to_owned(),
+    });
+    game.autosave_interval = Some(std::time::Duration::from_millis(100));
+    let game_event_tx = test.game_event_tx.clone();
+
+    database::set_kv(&test.db_conn, "current_game", "Game 1").unwrap();
+    database::record_save_slot(&test.db_conn, "Game 1", 2, Some("autosave"), "autosave").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        // Auto-saves are skipped until the game is ready and its auto-save has loaded
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        game_event_tx
+            .send(GameEvent::Ready {
+                game: "Game 1".to_owned(),
+            })
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        drop(tx);
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    expect_retroarch_commands(
+        &listener,
+        &["STATE_SLOT_PLUS", "STATE_SLOT_PLUS", "LOAD_STATE"],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn previous_game_is_not_resumed_in_streaming_mode() {
+    let (mut test, tx) = TestSetup::new();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
+
+    database::set_kv(&test.db_conn, "anarchy_mode", "streaming").unwrap();
+    database::set_kv(&test.db_conn, "current_game", "Game 1").unwrap();
+    drop(tx);
+
+    test.run_with_games(Some(games)).await.unwrap();
+    assert_eq!(test.game_runner_cmds.len(), 0);
+}
+
+#[tokio::test]
+async fn game_exits_are_announced() {
     let (mut test, mut tx) = TestSetup::new();
-    let user_name = "user_name".to_owned();
     let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
+    let game_event_tx = test.game_event_tx.clone();
 
     let join_handle = tokio::task::spawn(async move {
-        let movements = vec![Movement::Left];
         send_message(
             &mut tx,
             Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-
-                    // Set a duration >= 1 minute
-                    // We shouldn't execute the whole thing
-                    duration: 1000 * 60 * 2,
-                    stagger: 0,
-                    blocking: false,
-                }),
+                command: Command::Game("Game 1".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
+                privilege: Privilege::Moderator,
             },
         )
         .await;
 
-        // Make sure the above movement is able to start
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        for event in [
+            // Events about a game that isn't running are ignored
+            GameEvent::LaunchFailed {
+                game: "Game 2".to_owned(),
+                reason: "pre_launch hook exited with exit status: 1".to_owned(),
+            },
+            GameEvent::Exited {
+                game: "Game 1".to_owned(),
+                status: "exit status: 1".to_owned(),
+                restart: Some((std::time::Duration::from_secs(2), 1)),
+                max_retries: 3,
+            },
+            GameEvent::Restarted {
+                game: "Game 1".to_owned(),
+                attempt: 1,
+                max_retries: 3,
+            },
+            GameEvent::Exited {
+                game: "Game 1".to_owned(),
+                status: "exit status: 0".to_owned(),
+                restart: None,
+                max_retries: 3,
+            },
+        ] {
+            game_event_tx.send(event).unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
 
-        let movements = vec![Movement::Start];
-        send_message(
+        let reply = send_message(
             &mut tx,
             Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: false,
-                }),
+                command: Command::ListSlots,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
+                privilege: Privilege::Moderator,
             },
         )
         .await;
+        assert_eq!(reply, Some("No game is running".to_owned()));
     });
 
-    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
-    timeout.await.unwrap().unwrap();
-
+    test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    test.gamepad.expect_sequence(&[
-        (Movement::Left, ActionType::Press),
-        (Movement::Left, ActionType::Release),
-        (Movement::Start, ActionType::Press),
-        (Movement::Start, ActionType::Release),
-    ]);
+    assert_eq!(
+        test.announcements,
+        vec![
+            "Game 1 exited (exit status: 1), restarting in 2 seconds (attempt 1 of 3)".to_owned(),
+            "Restarted Game 1 (attempt 1 of 3)".to_owned(),
+            "Game 1 exited (exit status: 0)".to_owned(),
+        ]
+    );
+    assert_eq!(
+        database::get_kv::<Option<String>>(&test.db_conn, "current_game")
+            .unwrap()
+            .flatten(),
+        None
+    );
 }
 
 #[tokio::test]
-async fn only_directional_movements_are_interrupted() {
+async fn previous_game_log_is_shown() {
     let (mut test, mut tx) = TestSetup::new();
-    let user_name = "user_name".to_owned();
     let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
 
-    let join_handle = tokio::task::spawn(async move {
-        let movements = vec![Movement::Left, Movement::B];
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
+    let dir = std::env::temp_dir().join(format!("twitch-gamepad-log-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("Game_1")).unwrap();
+    std::fs::write(
+        dir.join("Game_1").join("20240101-000000.000.log"),
+        "loading\nsegmentation fault\n",
+    )
+    .unwrap();
+    test.game_logs = Some(GameLogConfig {
+        dir: dir.clone(),
+        ..Default::default()
+    });
 
-                    // Set a duration >= 1 minute
-                    // We shouldn't execute the whole thing
-                    duration: 400,
-                    stagger: 0,
-                    blocking: false,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
-            },
-        )
-        .await;
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
+    let game_event_tx = test.game_event_tx.clone();
 
-        // Make sure the above movement is able to start
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+    let join_handle = tokio::task::spawn(async move {
+        let log_msg = |privilege| Message {
+            command: Command::GameLog(5),
+            sender_id: user_id.clone(),
+            sender_name: user_name.clone(),
+            privilege,
+        };
+
+        let reply = send_message(&mut tx, log_msg(Privilege::Moderator)).await;
+        assert_eq!(reply, Some("No game has been started yet".to_owned()));
 
-        let movements = vec![Movement::Start];
         send_message(
             &mut tx,
             Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: false,
-                }),
+                command: Command::Game("Game 1".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
+                privilege: Privilege::Moderator,
             },
         )
         .await;
-    });
 
-    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
-    timeout.await.unwrap().unwrap();
+        // The newest session belongs to the running game, so the older one is shown
+        std::fs::write(
+            dir.join("Game_1").join("20240102-000000.000.log"),
+            "running\n",
+        )
+        .unwrap();
+        let reply = send_message(&mut tx, log_msg(Privilege::Moderator)).await;
+        assert_eq!(
+            reply,
+            Some("Previous session of Game 1: loading | segmentation fault".to_owned())
+        );
+
+        game_event_tx
+            .send(GameEvent::Exited {
+                game: "Game 1".to_owned(),
+                status: "exit status: 1".to_owned(),
+                restart: None,
+                max_retries: 3,
+            })
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let reply = send_message(&mut tx, log_msg(Privilege::Moderator)).await;
+        assert_eq!(
+            reply,
+            Some("Previous session of Game 1: running".to_owned())
+        );
+
+        let reply = send_message(&mut tx, log_msg(Privilege::Operator)).await;
+        assert_eq!(
+            reply,
+            Some("You don't have permission to do that".to_owned())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
 
+    test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
-
-    test.gamepad.expect_sequence(&[
-        (Movement::Left, ActionType::Press),
-        (Movement::B, ActionType::Press),
-        (Movement::Left, ActionType::Release),
-        (Movement::Start, ActionType::Press),
-        (Movement::Start, ActionType::Release),
-        (Movement::B, ActionType::Release),
-    ]);
 }
 
 #[tokio::test]
-async fn saving_cannot_be_interrupted() {
+async fn failed_launches_are_announced() {
     let (mut test, mut tx) = TestSetup::new();
-    let user_name = "user_name".to_owned();
     let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let listener = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let games = retroarch_game(&listener);
+    let game_event_tx = test.game_event_tx.clone();
 
     let join_handle = tokio::task::spawn(async move {
-        let movements = vec![Movement::Select];
         send_message(
             &mut tx,
             Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 250,
-                    stagger: 0,
-                    blocking: false,
-                }),
+                command: Command::Game("Game 1".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
+                privilege: Privilege::Moderator,
             },
         )
         .await;
 
-        send_message(
+        game_event_tx
+            .send(GameEvent::LaunchFailed {
+                game: "Game 1".to_owned(),
+                reason: "pre_launch hook exited with exit status: 1".to_owned(),
+            })
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let reply = send_message(
             &mut tx,
             Message {
-                command: Command::SaveState,
+                command: Command::ListSlots,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
+                privilege: Privilege::Moderator,
             },
         )
         .await;
+        assert_eq!(reply, Some("No game is running".to_owned()));
+    });
 
-        let movements = vec![Movement::Start];
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: false,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
-            },
-        )
-        .await;
-    });
-
-    test.run().await.unwrap();
+    test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
-    test.gamepad.expect_sequence(&[
-        (Movement::Select, ActionType::Press),
-        (Movement::Select, ActionType::Release),
-        (Movement::Mode, ActionType::Press),
-        (Movement::A, ActionType::Press),
-        (Movement::A, ActionType::Release),
-        (Movement::Mode, ActionType::Release),
-        (Movement::Start, ActionType::Press),
-        (Movement::Start, ActionType::Release),
-    ]);
+
+    assert_eq!(
+        test.announcements,
+        vec!["Failed to start Game 1: pre_launch hook exited with exit status: 1".to_owned()]
+    );
+    assert_eq!(
+        database::get_kv::<Option<String>>(&test.db_conn, "current_game")
+            .unwrap()
+            .flatten(),
+        None
+    );
 }
 
-#[tokio::test]
-async fn same_button_presses_are_sequenced() {
+async fn play_while_loading(while_loading: LoadingInput) -> TestSetup {
     let (mut test, mut tx) = TestSetup::new();
-    let user_name = "user_name".to_owned();
     let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    games.insert(
+        "Game 1".to_owned(),
+        GameInfo {
+            command: GameCommandString("cmdforgame1 --command".to_owned()),
+            ready: Some(ReadyCheck::Output {
+                contains: "loaded".to_owned(),
+            }),
+            while_loading: Some(while_loading),
+            ..Default::default()
+        },
+    );
+    let game_event_tx = test.game_event_tx.clone();
 
     let join_handle = tokio::task::spawn(async move {
-        let movements = vec![Movement::A];
+        let msg = |command, privilege| Message {
+            command,
+            sender_id: user_id.clone(),
+            sender_name: user_name.clone(),
+            privilege,
+        };
+
         send_message(
             &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements: movements.clone(),
-                    duration: 100,
-                    stagger: 0,
-                    blocking: false,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
-            },
+            msg(Command::Game("Game 1".to_owned()), Privilege::Moderator),
         )
         .await;
-
         send_message(
             &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements: movements.clone(),
-                    duration: 100,
-                    stagger: 0,
-                    blocking: false,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
-            },
+            msg(single_movement(Movement::A), Privilege::Standard),
         )
         .await;
+        let reply = send_message(&mut tx, msg(Command::PrintGameStatus, Privilege::Standard)).await;
+        assert_eq!(reply, Some("Game 1 is loading".to_owned()));
+
+        // Readiness of another game is ignored
+        for game in ["Game 2", "Game 1"] {
+            game_event_tx
+                .send(GameEvent::Ready {
+                    game: game.to_owned(),
+                })
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
+        let reply = send_message(&mut tx, msg(Command::PrintGameStatus, Privilege::Standard)).await;
+        assert_eq!(reply, Some("Game 1 is ready".to_owned()));
         send_message(
             &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: false,
-                }),
-                sender_id: user_id.clone(),
-                sender_name: user_name.clone(),
-                privilege: Privilege::Broadcaster,
-            },
+            msg(single_movement(Movement::B), Privilege::Standard),
         )
         .await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     });
 
-    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
-    timeout.await.unwrap().unwrap();
-
+    test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
+    assert_eq!(test.announcements, vec!["Game 1 is ready".to_owned()]);
+    test
+}
+
+#[tokio::test]
+async fn movements_are_discarded_while_loading() {
+    let test = play_while_loading(LoadingInput::Discard).await;
+    test.gamepad.expect_sequence(&[
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn movements_are_held_while_loading() {
+    let test = play_while_loading(LoadingInput::Hold).await;
     test.gamepad.expect_sequence(&[
         (Movement::A, ActionType::Press),
         (Movement::A, ActionType::Release),
-        (Movement::A, ActionType::Press),
-        (Movement::A, ActionType::Release),
-        (Movement::A, ActionType::Press),
-        (Movement::A, ActionType::Release),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
     ]);
 }
 
 #[tokio::test]
-async fn sfx_are_enabled_in_stream_mode_and_games_cannot_be_started() {
+async fn moderator_can_switch_games() {
     let (mut test, mut tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
     let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
 
+    let name: GameName = "Game 1".to_owned();
+    games.insert(
+        name,
+        GameInfo {
+            command: GameCommandString("cmdforgame1 --command".to_owned()),
+            ..Default::default()
+        },
+    );
+
     let name: GameName = "Game 2".to_owned();
     let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
     games.insert(
         name,
         GameInfo {
             command: game2_cmd.clone(),
-            restricted_inputs: Some(vec!["start".to_owned()]),
-            controls: None,
+            ..Default::default()
         },
     );
 
@@ -1984,105 +2369,396 @@ async fn sfx_are_enabled_in_stream_mode_and_games_cannot_be_started() {
         send_message(
             &mut tx,
             Message {
-                command: Command::SetAnarchyMode(AnarchyType::Streaming),
+                command: Command::Game("Game 2".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(
+        test.game_runner_cmds[0],
+        GameRunner::SwitchTo(Box::new(game2_cmd.to_command("Game 2")))
+    );
+}
+
+#[tokio::test]
+async fn game_commands_are_split_like_a_shell() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    games.insert(
+        "Game 1".to_owned(),
+        GameInfo {
+            command: GameCommandString(
+                r#"retroarch -L "cores/snes9x.so" /roms/Super\ Game\ (USA).sfc"#.to_owned(),
+            ),
+            args: Some(vec!["--config".to_owned(), "my config.cfg".to_owned()]),
+            env: Some(BTreeMap::from([(
+                "SDL_AUDIODRIVER".to_owned(),
+                "pulse".to_owned(),
+            )])),
+            cwd: Some("/srv/games".into()),
+            ..Default::default()
+        },
+    );
 
+    let join_handle = tokio::task::spawn(async move {
         send_message(
             &mut tx,
             Message {
-                command: Command::Game("Game 2".to_owned()),
+                command: Command::Game("Game 1".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+    });
 
-        let movements = vec![Movement::Start, Movement::B];
-        send_message(
-            &mut tx,
-            Message {
-                command: Command::Movement(MovementPacket {
-                    movements,
-                    duration: 50,
-                    stagger: 0,
-                    blocking: true,
-                }),
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    let command = match &test.game_runner_cmds[..] {
+        [GameRunner::SwitchTo(command)] => command,
+        cmds => panic!("unexpected game runner commands {:?}", cmds),
+    };
+    assert_eq!(command.command, "retroarch");
+    assert_eq!(
+        command.args,
+        vec![
+            "-L",
+            "cores/snes9x.so",
+            "/roms/Super Game (USA).sfc",
+            "--config",
+            "my config.cfg"
+        ]
+    );
+    assert_eq!(command.env["SDL_AUDIODRIVER"], "pulse");
+    assert_eq!(command.cwd, Some("/srv/games".into()));
+}
+
+#[tokio::test]
+async fn games_are_found_by_tag_search_and_near_misses() {
+    let (mut test, tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    for (name, display_name, tags) in [
+        ("smw", Some("Super Mario World"), vec!["snes", "platformer"]),
+        ("Super Metroid", None, vec!["snes"]),
+        ("zelda-oot", Some("Ocarina of Time"), vec!["n64"]),
+    ] {
+        games.insert(
+            name.to_owned(),
+            GameInfo {
+                command: GameCommandString(format!("cmdfor{}", name)),
+                display_name: display_name.map(|name| name.to_owned()),
+                tags: Some(tags.into_iter().map(|tag| tag.to_owned()).collect()),
+                ..Default::default()
+            },
+        );
+    }
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Moderator,
-            },
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        let reply = send(
+            Command::ListGames(Some("snes".to_owned())),
+            Privilege::Standard,
+        )
+        .await;
+        assert_eq!(reply, Some("Super Mario World, Super Metroid".to_owned()));
+        let reply = send(
+            Command::ListGames(Some("gba".to_owned())),
+            Privilege::Standard,
+        )
+        .await;
+        assert_eq!(reply, Some("No games are tagged gba".to_owned()));
+        let reply = send(Command::SearchGames("time".to_owned()), Privilege::Standard).await;
+        assert_eq!(reply, Some("Ocarina of Time".to_owned()));
+
+        let reply = send(Command::Game("super".to_owned()), Privilege::Moderator).await;
+        assert_eq!(
+            reply,
+            Some("No game super found, did you mean Super Metroid, Super Mario World?".to_owned())
+        );
+        let reply = send(
+            Command::Game("super metriod".to_owned()),
+            Privilege::Moderator,
+        )
+        .await;
+        assert_eq!(reply, Some("Switching to Super Metroid".to_owned()));
+        let reply = send(
+            Command::RandomGame(Some("n64".to_owned())),
+            Privilege::Moderator,
         )
         .await;
+        assert_eq!(reply, Some("Switching to Ocarina of Time".to_owned()));
+        let reply = send(Command::RandomGame(None), Privilege::Standard).await;
+        assert_eq!(
+            reply,
+            Some("You don't have permission to do that".to_owned())
+        );
+
+        drop(tx);
     });
 
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    dbg!(&test);
+    let names: Vec<&str> = test
+        .game_runner_cmds
+        .iter()
+        .map(|cmd| match cmd {
+            GameRunner::SwitchTo(command) => command.name.as_str(),
+            cmd => panic!("unexpected game runner command {:?}", cmd),
+        })
+        .collect();
+    assert_eq!(names, vec!["Super Metroid", "zelda-oot"]);
+}
 
-    assert_eq!(test.game_runner_cmds.len(), 1);
-    assert_eq!(test.game_runner_cmds[0], GameRunner::Stop);
-    assert_eq!(test.sfx_cmds.len(), 2);
-    assert_eq!(test.sfx_cmds[0], SfxRequest::Enable(false));
-    assert_eq!(test.sfx_cmds[1], SfxRequest::Enable(true));
-    test.gamepad.expect_sequence(&[]);
+#[tokio::test]
+async fn long_game_lists_are_cut_short() {
+    let (mut test, mut tx) = TestSetup::new();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+    for n in 0..100 {
+        games.insert(
+            format!("Some Game With A Long Name {:02}", n),
+            GameInfo {
+                command: GameCommandString("cmdforgame".to_owned()),
+                ..Default::default()
+            },
+        );
+    }
+
+    let join_handle = tokio::task::spawn(async move {
+        let response = send_message(
+            &mut tx,
+            Message {
+                command: Command::ListGames(None),
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(response.len() <= 450);
+        assert!(response.starts_with("Some Game With A Long Name 00, "));
+        assert!(response
+            .ends_with(" more, narrow it down with tp games <tag> or tp game search <text>"));
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
 }
 
 #[tokio::test]
-async fn games_can_be_started_after_switching_from_stream_mode() {
+async fn moderator_can_stop_gameplay() {
     let (mut test, mut tx) = TestSetup::new();
     let user_id = "user_id".to_owned();
     let user_name = "user_name".to_owned();
 
     let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
 
+    let name: GameName = "Game 1".to_owned();
+    games.insert(
+        name,
+        GameInfo {
+            command: GameCommandString("cmdforgame1 --command".to_owned()),
+            ..Default::default()
+        },
+    );
+
     let name: GameName = "Game 2".to_owned();
     let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
     games.insert(
         name,
         GameInfo {
-            command: game2_cmd.clone(),
-            restricted_inputs: Some(vec!["start".to_owned()]),
-            controls: None,
+            command: game2_cmd,
+            ..Default::default()
         },
     );
 
-    // Initialize in streaming setting
-    database::set_kv(&test.db_conn, "anarchy_mode", "streaming").unwrap();
-
     let join_handle = tokio::task::spawn(async move {
         send_message(
             &mut tx,
             Message {
-                command: Command::SetAnarchyMode(AnarchyType::Democracy),
+                command: Command::Stop,
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
             },
         )
         .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(test.game_runner_cmds[0], GameRunner::Stop);
+}
+
+#[tokio::test]
+async fn user_cannot_switch_games() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 1".to_owned();
+    games.insert(
+        name,
+        GameInfo {
+            command: GameCommandString("cmdforgame1 --command".to_owned()),
+            ..Default::default()
+        },
+    );
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd,
+            ..Default::default()
+        },
+    );
 
+    let join_handle = tokio::task::spawn(async move {
         send_message(
             &mut tx,
             Message {
                 command: Command::Game("Game 2".to_owned()),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
-                privilege: Privilege::Moderator,
+                privilege: Privilege::Standard,
             },
         )
         .await;
+    });
 
-        send_message(
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 0);
+}
+
+#[tokio::test]
+async fn user_cannot_stop_gameplay() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 1".to_owned();
+    games.insert(
+        name,
+        GameInfo {
+            command: GameCommandString("cmdforgame1 --command".to_owned()),
+            ..Default::default()
+        },
+    );
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd,
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
             &mut tx,
             Message {
-                command: single_movement(Movement::A),
+                command: Command::Stop,
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 0);
+}
+
+#[tokio::test]
+async fn restricted_inputs_are_blocked_in_normal_modes() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["start".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        let movements = vec![Movement::Start, Movement::B];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: true,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
                 sender_id: user_id.clone(),
                 sender_name: user_name.clone(),
                 privilege: Privilege::Moderator,
@@ -2094,17 +2770,1875 @@ async fn games_can_be_started_after_switching_from_stream_mode() {
     test.run_with_games(Some(games)).await.unwrap();
     join_handle.await.unwrap();
 
-    dbg!(&test);
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(
+        test.game_runner_cmds[0],
+        GameRunner::SwitchTo(Box::new(game2_cmd.to_command("Game 2")))
+    );
+    test.gamepad.expect_sequence(&[]);
+}
+
+#[tokio::test]
+async fn restricted_inputs_are_not_blocked_in_restricted_mode() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let op_id = "op_id".to_owned();
+    let op_name = "op_name".to_owned();
+
+    database::update_user(&test.db_conn, &op_id, &op_name).unwrap();
+    database::op_user(&mut test.db_conn, &op_name).unwrap();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["start".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::SetAnarchyMode(AnarchyType::Restricted),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        let movements = vec![Movement::Start, Movement::B];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: true,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: op_id.clone(),
+                sender_name: op_name.clone(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
 
     assert_eq!(test.game_runner_cmds.len(), 1);
     assert_eq!(
         test.game_runner_cmds[0],
-        GameRunner::SwitchTo(game2_cmd.to_command())
+        GameRunner::SwitchTo(Box::new(game2_cmd.to_command("Game 2")))
     );
-    assert_eq!(test.sfx_cmds.len(), 1);
-    assert_eq!(test.sfx_cmds[0], SfxRequest::Enable(false));
     test.gamepad.expect_sequence(&[
-        (Movement::A, ActionType::Press),
-        (Movement::A, ActionType::Release),
+        (Movement::Start, ActionType::Press),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+        (Movement::Start, ActionType::Release),
     ]);
 }
+
+#[tokio::test]
+async fn users_cannot_send_input_in_restricted_mode() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mod_id = "mod_id".to_owned();
+    let mod_name = "mod_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["start".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: mod_id.clone(),
+                sender_name: mod_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::SetAnarchyMode(AnarchyType::Restricted),
+                sender_id: mod_id.clone(),
+                sender_name: mod_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: single_movement(Movement::A),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(
+        test.game_runner_cmds[0],
+        GameRunner::SwitchTo(Box::new(game2_cmd.to_command("Game 2")))
+    );
+    test.gamepad.expect_sequence(&[]);
+}
+
+#[tokio::test]
+async fn can_interrupt_movements_with_direction() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        let movements = vec![Movement::Left];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+
+                    // Set a duration >= 1 minute
+                    // We shouldn't execute the whole thing
+                    duration: 1000 * 60 * 2,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        // Make sure the above movement is able to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let movements = vec![Movement::Start];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::Left, ActionType::Press),
+        (Movement::Left, ActionType::Release),
+        (Movement::Start, ActionType::Press),
+        (Movement::Start, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn chains_are_not_interleaved_with_other_movements() {
+    let (mut test, tx) = TestSetup::new();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |user: &str, input: &str| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command: command::parse_command(input).unwrap(),
+                sender_id: user.to_owned(),
+                sender_name: user.to_owned(),
+                privilege: Privilege::Standard,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send("u1", "down>right>a").await;
+        // Directions would usually interrupt the chain
+        send("u2", "up").await;
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(3), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::Down, ActionType::Press),
+        (Movement::Down, ActionType::Release),
+        (Movement::Right, ActionType::Press),
+        (Movement::Right, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::Up, ActionType::Press),
+        (Movement::Up, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn packets_queued_behind_chains_are_bounded() {
+    let (mut test, tx) = TestSetup::new();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |input: &str| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command: command::parse_command(input).unwrap(),
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Standard,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send("a 300ms > b 300ms").await;
+        // Only the newest packets are kept while the chain plays
+        send("y 20ms").await;
+        for _ in 0..20 {
+            send("x 20ms").await;
+        }
+        // Chains that take too long to play are ignored
+        send("a 5 > b 5 > a 5").await;
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(5), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    let mut expected = vec![
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+    ];
+    for _ in 0..20 {
+        expected.push((Movement::X, ActionType::Press));
+        expected.push((Movement::X, ActionType::Release));
+    }
+    test.gamepad.expect_sequence(&expected);
+}
+
+#[tokio::test]
+async fn queued_hotkeys_are_never_dropped() {
+    let (mut test, tx) = TestSetup::new();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Moderator,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send(command::parse_command("a 300ms > b 300ms").unwrap()).await;
+        send(Command::Emulator(EmulatorAction::SaveState)).await;
+        for _ in 0..25 {
+            send(command::parse_command("x 20ms").unwrap()).await;
+        }
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(5), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    let mut expected = vec![
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+        (Movement::Mode, ActionType::Press),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::Mode, ActionType::Release),
+    ];
+    // The save takes up one place in the queue
+    for _ in 0..19 {
+        expected.push((Movement::X, ActionType::Press));
+        expected.push((Movement::X, ActionType::Release));
+    }
+    test.gamepad.expect_sequence(&expected);
+}
+
+#[tokio::test]
+async fn repeats_are_capped_per_game() {
+    let (mut test, tx) = TestSetup::new();
+
+    let games: BTreeMap<GameName, GameInfo> = [(
+        "Game 1".to_owned(),
+        GameInfo {
+            command: GameCommandString("cmdforgame".to_owned()),
+            max_repeats: Some(3),
+            max_mash_rate: Some(20),
+            ..Default::default()
+        },
+    )]
+    .into_iter()
+    .collect();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Moderator,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send(Command::Game("Game 1".to_owned())).await;
+        send(command::parse_command("a*5").unwrap()).await;
+        send(command::parse_command("b").unwrap()).await;
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(3),
+        test.run_with_games(Some(games)),
+    );
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn held_buttons_stay_pressed_until_released() {
+    let (mut test, tx) = TestSetup::new();
+
+    let games: BTreeMap<GameName, GameInfo> = [(
+        "Game 1".to_owned(),
+        GameInfo {
+            command: GameCommandString("cmdforgame".to_owned()),
+            holds: Some(HoldConfig {
+                timeout: None,
+                privilege: Some(
+                    [
+                        (Movement::B, Privilege::Standard),
+                        (Movement::TR, Privilege::Moderator),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+            }),
+            ..Default::default()
+        },
+    )]
+    .into_iter()
+    .collect();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send(Command::Game("Game 1".to_owned()), Privilege::Moderator).await;
+        assert_eq!(
+            send(
+                command::parse_command("hold tr").unwrap(),
+                Privilege::Standard
+            )
+            .await,
+            Some("You can't hold tr".to_owned())
+        );
+        assert_eq!(
+            send(
+                command::parse_command("hold a").unwrap(),
+                Privilege::Standard
+            )
+            .await,
+            Some("You can't hold a".to_owned())
+        );
+        assert_eq!(
+            send(
+                command::parse_command("hold b").unwrap(),
+                Privilege::Standard
+            )
+            .await,
+            None
+        );
+        send(
+            command::parse_command("hold tr").unwrap(),
+            Privilege::Moderator,
+        )
+        .await;
+        send(command::parse_command("b").unwrap(), Privilege::Standard).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+        assert_eq!(
+            send(
+                command::parse_command("release tr").unwrap(),
+                Privilege::Standard
+            )
+            .await,
+            Some("You can't release tr".to_owned())
+        );
+        assert_eq!(
+            send(
+                command::parse_command("release all").unwrap(),
+                Privilege::Standard
+            )
+            .await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        send(
+            command::parse_command("release b").unwrap(),
+            Privilege::Standard,
+        )
+        .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(3),
+        test.run_with_games(Some(games)),
+    );
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    // The tap on B doesn't release it while it's held, TR stays held until the end
+    test.gamepad.expect_sequence(&[
+        (Movement::B, ActionType::Press),
+        (Movement::TR, ActionType::Press),
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+        (Movement::TR, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn aliases_resolve_against_the_current_game() {
+    let (mut test, tx) = TestSetup::new();
+
+    let aliases = |aliases: &[(&str, &str)]| {
+        aliases
+            .iter()
+            .map(|(word, movement)| (word.to_string(), movement.to_string()))
+            .collect()
+    };
+    let games: BTreeMap<GameName, GameInfo> = [
+        (
+            "Game 1".to_owned(),
+            GameInfo {
+                command: GameCommandString("cmdforgame".to_owned()),
+                aliases: Some(aliases(&[("Jump", "b"), ("hadouken", "down>right>a")])),
+                ..Default::default()
+            },
+        ),
+        (
+            "Game 2".to_owned(),
+            GameInfo {
+                command: GameCommandString("cmdforgame2".to_owned()),
+                aliases: Some(aliases(&[("jump", "a")])),
+                ..Default::default()
+            },
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let alias_words = ["jump", "hadouken"]
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |input: &str| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command: command::parse_command_with_aliases(input, &alias_words).unwrap(),
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Moderator,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        // No game is running, so there's nothing to resolve against
+        send("jump").await;
+        send("tp game Game 1").await;
+        assert_eq!(
+            send("tp controls").await,
+            Some("Game 1 aliases: hadouken = down>right>a, jump = b".to_owned())
+        );
+        send("jump").await;
+        send("hadouken").await;
+        send("tp game Game 2").await;
+        send("jump 0.2").await;
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(3),
+        test.run_with_games(Some(games)),
+    );
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::B, ActionType::Press),
+        (Movement::Down, ActionType::Press),
+        (Movement::B, ActionType::Release),
+        (Movement::Down, ActionType::Release),
+        (Movement::Right, ActionType::Press),
+        (Movement::Right, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn chat_mentioning_aliases_is_ignored() {
+    let (mut test, mut tx) = TestSetup::new();
+    let alias_words = ["jump".to_owned()].into_iter().collect();
+
+    assert_eq!(
+        command::parse_command_with_aliases("I want to jump lol", &alias_words),
+        None
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        // No game is running, so this doesn't mean anything
+        let reply = send_message(
+            &mut tx,
+            Message {
+                command: command::parse_command_with_aliases("jump", &alias_words).unwrap(),
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+        assert_eq!(reply, None);
+    });
+
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[]);
+    let users: i64 = test
+        .db_conn
+        .query_row("select count(*) from users", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(users, 0);
+}
+
+#[tokio::test]
+async fn only_directional_movements_are_interrupted() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        let movements = vec![Movement::Left, Movement::B];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+
+                    // Set a duration >= 1 minute
+                    // We shouldn't execute the whole thing
+                    duration: 400,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        // Make sure the above movement is able to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let movements = vec![Movement::Start];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::Left, ActionType::Press),
+        (Movement::B, ActionType::Press),
+        (Movement::Left, ActionType::Release),
+        (Movement::Start, ActionType::Press),
+        (Movement::Start, ActionType::Release),
+        (Movement::B, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn saving_cannot_be_interrupted() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        let movements = vec![Movement::Select];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 250,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Emulator(EmulatorAction::SaveState),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        let movements = vec![Movement::Start];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+    });
+
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+    test.gamepad.expect_sequence(&[
+        (Movement::Select, ActionType::Press),
+        (Movement::Select, ActionType::Release),
+        (Movement::Mode, ActionType::Press),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::Mode, ActionType::Release),
+        (Movement::Start, ActionType::Press),
+        (Movement::Start, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn same_button_presses_are_sequenced() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        let movements = vec![Movement::A];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements: movements.clone(),
+                    axes: vec![],
+                    duration: 100,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements: movements.clone(),
+                    axes: vec![],
+                    duration: 100,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn sfx_are_enabled_in_stream_mode_and_games_cannot_be_started() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["start".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::SetAnarchyMode(AnarchyType::Streaming),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        let movements = vec![Movement::Start, Movement::B];
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements,
+                    axes: vec![],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: true,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    dbg!(&test);
+
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(test.game_runner_cmds[0], GameRunner::Stop);
+    assert_eq!(test.sfx_cmds.len(), 2);
+    assert_eq!(test.sfx_cmds[0], SfxRequest::Enable(false));
+    assert_eq!(test.sfx_cmds[1], SfxRequest::Enable(true));
+    test.gamepad.expect_sequence(&[]);
+}
+
+#[tokio::test]
+async fn games_can_be_started_after_switching_from_stream_mode() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["start".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    // Initialize in streaming setting
+    database::set_kv(&test.db_conn, "anarchy_mode", "streaming").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::SetAnarchyMode(AnarchyType::Democracy),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: single_movement(Movement::A),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+    });
+
+    test.run_with_games(Some(games)).await.unwrap();
+    join_handle.await.unwrap();
+
+    dbg!(&test);
+
+    assert_eq!(test.game_runner_cmds.len(), 1);
+    assert_eq!(
+        test.game_runner_cmds[0],
+        GameRunner::SwitchTo(Box::new(game2_cmd.to_command("Game 2")))
+    );
+    assert_eq!(test.sfx_cmds.len(), 1);
+    assert_eq!(test.sfx_cmds[0], SfxRequest::Enable(false));
+    test.gamepad.expect_sequence(&[
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+    ]);
+}
+
+#[tokio::test]
+async fn stick_returns_to_center_after_duration() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements: vec![Movement::A],
+                    axes: vec![(Axis::X, 255), (Axis::Y, 128)],
+                    duration: 200,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::A, ActionType::Press),
+        (Movement::A, ActionType::Release),
+    ]);
+    test.gamepad.expect_axis_sequence(&[
+        (Axis::X, 255),
+        (Axis::Y, 128),
+        (Axis::X, 128),
+        (Axis::Y, 128),
+    ]);
+}
+
+#[tokio::test]
+async fn stick_movements_interrupt_held_stick() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements: vec![],
+                    axes: vec![(Axis::X, 0), (Axis::Y, 128)],
+
+                    // Long enough that it must be interrupted to finish in time
+                    duration: 1000 * 60 * 2,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+
+        // Make sure the above movement is able to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Movement(MovementPacket {
+                    movements: vec![],
+                    axes: vec![(Axis::X, 128), (Axis::Y, 0)],
+                    duration: 50,
+                    stagger: 0,
+                    blocking: false,
+                    chain: vec![],
+                    repeat: None,
+                    hold: None,
+                    frames: None,
+                }),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Broadcaster,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[]);
+    test.gamepad.expect_axis_sequence(&[
+        (Axis::X, 0),
+        (Axis::Y, 128),
+        (Axis::X, 128),
+        (Axis::Y, 128),
+        (Axis::X, 128),
+        (Axis::Y, 0),
+        (Axis::X, 128),
+        (Axis::Y, 128),
+    ]);
+}
+
+#[tokio::test]
+async fn triggers_return_to_rest_after_duration() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_name = "user_name".to_owned();
+    let user_id = "user_id".to_owned();
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: command::parse_command("rt 50% rs up").unwrap(),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[]);
+    test.gamepad.expect_axis_sequence(&[
+        (Axis::RZ, 128),
+        (Axis::RX, 128),
+        (Axis::RY, command::AXIS_MIN),
+        (Axis::RX, 128),
+        (Axis::RY, 128),
+        (Axis::RZ, 0),
+    ]);
+}
+
+#[tokio::test]
+async fn restricted_analog_inputs_are_blocked() {
+    let (mut test, mut tx) = TestSetup::new();
+    let user_id = "user_id".to_owned();
+    let user_name = "user_name".to_owned();
+
+    let mut games: BTreeMap<GameName, GameInfo> = BTreeMap::new();
+
+    let name: GameName = "Game 2".to_owned();
+    let game2_cmd = GameCommandString("cmdforgame2 --command".to_owned());
+    games.insert(
+        name,
+        GameInfo {
+            command: game2_cmd.clone(),
+            restricted_inputs: Some(vec!["rs".to_owned(), "lt".to_owned()]),
+            ..Default::default()
+        },
+    );
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::Game("Game 2".to_owned()),
+                sender_id: user_id.clone(),
+                sender_name: user_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        for cmd in ["rs up", "lt 50%", "lt", "ls down"] {
+            send_message(
+                &mut tx,
+                Message {
+                    command: command::parse_command(cmd).unwrap(),
+                    sender_id: user_id.clone(),
+                    sender_name: user_name.clone(),
+                    privilege: Privilege::Moderator,
+                },
+            )
+            .await;
+        }
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(2),
+        test.run_with_games(Some(games)),
+    );
+    timeout.await.unwrap().unwrap();
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[]);
+    test.gamepad.expect_axis_sequence(&[
+        (Axis::X, 128),
+        (Axis::Y, 255),
+        (Axis::X, 128),
+        (Axis::Y, 128),
+    ]);
+}
+
+#[tokio::test]
+async fn voting_mode_sends_only_the_winning_movement() {
+    let (mut test, mut tx) = TestSetup::new();
+    let mod_id = "mod_id".to_owned();
+    let mod_name = "mod_name".to_owned();
+
+    database::set_kv(&test.db_conn, "anarchy_mode", "voting").unwrap();
+    database::set_kv(&test.db_conn, "vote_window", "200").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        for (user, movement) in [
+            ("u1", Movement::A),
+            ("u2", Movement::B),
+            ("u3", Movement::B),
+            ("u1", Movement::B),
+            ("u4", Movement::A),
+        ] {
+            send_message(
+                &mut tx,
+                Message {
+                    command: single_movement(movement),
+                    sender_id: user.to_owned(),
+                    sender_name: user.to_owned(),
+                    privilege: Privilege::Standard,
+                },
+            )
+            .await;
+        }
+
+        // Let the vote close before shutting down
+        tokio::time::sleep(tokio::time::Duration::from_millis(400)).await;
+
+        let response = send_message(
+            &mut tx,
+            Message {
+                command: Command::PrintVoteSettings,
+                sender_id: mod_id.clone(),
+                sender_name: mod_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        assert_eq!(
+            response,
+            Some(
+                "Vote window is 0.2 seconds, votes need at least 1 votes, ties are broken by first"
+                    .to_owned()
+            )
+        );
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[
+        (Movement::B, ActionType::Press),
+        (Movement::B, ActionType::Release),
+    ]);
+    assert_eq!(
+        test.announcements,
+        vec!["Vote result: b 0.05s (3), a 0.05s (1)".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn voting_mode_respects_minimum_votes_and_ties() {
+    let (mut test, mut tx) = TestSetup::new();
+    let mod_id = "mod_id".to_owned();
+    let mod_name = "mod_name".to_owned();
+
+    database::set_kv(&test.db_conn, "anarchy_mode", "voting").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        for command in [
+            Command::SetVoteWindow(chrono::Duration::milliseconds(100)),
+            Command::SetVoteMinVotes(3),
+        ] {
+            send_message(
+                &mut tx,
+                Message {
+                    command,
+                    sender_id: mod_id.clone(),
+                    sender_name: mod_name.clone(),
+                    privilege: Privilege::Moderator,
+                },
+            )
+            .await;
+        }
+
+        for (user, movement) in [("u1", Movement::A), ("u2", Movement::B)] {
+            send_message(
+                &mut tx,
+                Message {
+                    command: single_movement(movement),
+                    sender_id: user.to_owned(),
+                    sender_name: user.to_owned(),
+                    privilege: Privilege::Standard,
+                },
+            )
+            .await;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::SetVoteTieBreak(crate::vote::TieBreak::Skip),
+                sender_id: mod_id.clone(),
+                sender_name: mod_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        for (user, movement) in [
+            ("u1", Movement::A),
+            ("u2", Movement::B),
+            ("u3", Movement::X),
+        ] {
+            send_message(
+                &mut tx,
+                Message {
+                    command: single_movement(movement),
+                    sender_id: user.to_owned(),
+                    sender_name: user.to_owned(),
+                    privilege: Privilege::Standard,
+                },
+            )
+            .await;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+    });
+
+    let timeout = tokio::time::timeout(tokio::time::Duration::from_secs(2), test.run());
+    timeout.await.unwrap().unwrap();
+    join_handle.await.unwrap();
+
+    test.gamepad.expect_sequence(&[]);
+    assert_eq!(
+        test.announcements,
+        vec![
+            "Vote failed, 2 of 3 required votes".to_owned(),
+            "Vote tied, no input sent: a 0.05s (1), b 0.05s (1), x 0.05s (1)".to_owned(),
+        ]
+    );
+
+    let min_votes: String = database::get_kv(&test.db_conn, "vote_min_votes")
+        .unwrap()
+        .unwrap();
+    assert_eq!(min_votes, "3");
+    let tie_break: String = database::get_kv(&test.db_conn, "vote_tie_break")
+        .unwrap()
+        .unwrap();
+    assert_eq!(tie_break, "skip");
+}
+
+fn poll_games() -> BTreeMap<GameName, GameInfo> {
+    ["Game 1", "Game 2", "Game 3"]
+        .into_iter()
+        .map(|name| {
+            (
+                name.to_owned(),
+                GameInfo {
+                    command: GameCommandString("cmdforgame".to_owned()),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn viewers_can_vote_for_the_next_game() {
+    let (mut test, tx) = TestSetup::new();
+    database::set_kv(&test.db_conn, "game_poll_window", "200").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |user: &str, command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: user.to_owned(),
+                sender_name: user.to_owned(),
+                privilege: Privilege::Standard,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        assert_eq!(
+            send("u1", Command::VoteGame(1)).await,
+            Some("No game poll is open, nominate a game with tp nominate <game>".to_owned())
+        );
+        assert_eq!(
+            send("u1", Command::Nominate("game 2".to_owned())).await,
+            Some("Game poll open for 0.2 seconds with Game 2 as option 1, nominate more with tp nominate <game> and vote with tp votegame <number>".to_owned())
+        );
+        assert_eq!(
+            send("u2", Command::Nominate("Game 3".to_owned())).await,
+            Some("Nominated Game 3 as option 2".to_owned())
+        );
+        assert_eq!(
+            send("u2", Command::Nominate("game 2".to_owned())).await,
+            Some("Game 2 is already option 1".to_owned())
+        );
+
+        assert_eq!(send("u1", Command::VoteGame(2)).await, None);
+        assert_eq!(send("u2", Command::VoteGame(2)).await, None);
+        assert_eq!(send("u3", Command::VoteGame(1)).await, None);
+        assert_eq!(
+            send("u3", Command::VoteGame(5)).await,
+            Some("Pick an option from 1 to 2: 1 Game 2 (1), 2 Game 3 (2)".to_owned())
+        );
+
+        // Let the poll close before shutting down
+        tokio::time::sleep(tokio::time::Duration::from_millis(400)).await;
+        assert_eq!(
+            send("u1", Command::PrintGamePoll).await,
+            Some("No game poll is open, polls last 0.2 seconds, need at least 3 votes and have up to 5 options".to_owned())
+        );
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(2),
+        test.run_with_games(Some(poll_games())),
+    );
+    timeout.await.unwrap().unwrap();
+    join_handle.await.unwrap();
+
+    match &test.game_runner_cmds[..] {
+        [GameRunner::SwitchTo(command)] => assert_eq!(command.name, "Game 3"),
+        cmds => panic!("unexpected game runner commands {:?}", cmds),
+    }
+    assert_eq!(
+        test.announcements,
+        vec!["Game vote result: Game 3 (2), Game 2 (1), switching to Game 3".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn single_viewers_cannot_switch_games() {
+    let (mut test, tx) = TestSetup::new();
+    database::set_kv(&test.db_conn, "game_poll_window", "200").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "u1".to_owned(),
+                sender_name: "u1".to_owned(),
+                privilege: Privilege::Standard,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        send(Command::Nominate("game 2".to_owned())).await;
+        assert_eq!(send(Command::VoteGame(1)).await, None);
+        tokio::time::sleep(tokio::time::Duration::from_millis(400)).await;
+
+        // Opening polls over and over is limited too
+        assert_eq!(
+            send(Command::Nominate("game 2".to_owned())).await,
+            Some("You can open another game poll in 599 seconds".to_owned())
+        );
+        drop(tx);
+    });
+
+    let timeout = tokio::time::timeout(
+        tokio::time::Duration::from_secs(2),
+        test.run_with_games(Some(poll_games())),
+    );
+    timeout.await.unwrap().unwrap();
+    join_handle.await.unwrap();
+
+    assert!(test.game_runner_cmds.is_empty());
+    assert_eq!(
+        test.announcements,
+        vec!["Game vote failed, 1 of 3 required votes".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn moderators_can_start_and_cancel_game_polls() {
+    let (mut test, tx) = TestSetup::new();
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        assert_eq!(
+            send(Command::StartGamePoll(None), Privilege::Operator).await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        assert_eq!(
+            send(Command::SetGamePollOptions(2), Privilege::Moderator).await,
+            Some("Game polls now have up to 2 options".to_owned())
+        );
+
+        let reply = send(Command::StartGamePoll(None), Privilege::Moderator)
+            .await
+            .unwrap();
+        assert!(reply
+            .starts_with("Game poll open for 60 seconds, vote with tp votegame <number>: 1 Game "));
+        assert!(reply.contains(" (0), 2 Game "));
+        assert_eq!(
+            send(Command::StartGamePoll(None), Privilege::Moderator).await,
+            Some("A game poll is already open, cancel it with tp gamepoll cancel".to_owned())
+        );
+
+        assert_eq!(
+            send(Command::CancelGamePoll, Privilege::Moderator).await,
+            Some("Cancelled the game poll".to_owned())
+        );
+        assert_eq!(
+            send(Command::CancelGamePoll, Privilege::Moderator).await,
+            Some("No game poll is open".to_owned())
+        );
+        assert_eq!(
+            send(
+                Command::StartGamePoll(Some("gba".to_owned())),
+                Privilege::Moderator
+            )
+            .await,
+            Some("Not enough games to vote on".to_owned())
+        );
+        drop(tx);
+    });
+
+    test.run_with_games(Some(poll_games())).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert!(test.game_runner_cmds.is_empty());
+    assert!(test.announcements.is_empty());
+    let options: String = database::get_kv(&test.db_conn, "game_poll_options")
+        .unwrap()
+        .unwrap();
+    assert_eq!(options, "2");
+}
+
+/// A slot running all day, every day
+fn all_day_slot() -> ScheduleSlot {
+    ScheduleSlot {
+        name: None,
+        days: vec![],
+        start: chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        end: chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        game: Some("Game 2".to_owned()),
+        mode: Some(AnarchyType::Anarchy),
+    }
+}
+
+#[tokio::test]
+async fn scheduled_slots_switch_game_and_mode() {
+    let (mut test, tx) = TestSetup::new();
+    test.schedule = Some(vec![all_day_slot()]);
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command, privilege: Privilege| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        // Let the schedule be checked before any messages
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let reply = send(Command::PrintSchedule, Privilege::Standard)
+            .await
+            .unwrap();
+        assert!(reply.starts_with("Now: Game 2 in anarchy mode until 00:00, next: "));
+
+        assert_eq!(
+            send(Command::SkipScheduledSlot, Privilege::Operator).await,
+            Some("You don't have permission to do that".to_owned())
+        );
+        assert_eq!(
+            send(Command::SkipScheduledSlot, Privilege::Moderator).await,
+            Some("Skipped Game 2 in anarchy mode".to_owned())
+        );
+        drop(tx);
+    });
+
+    test.run_with_games(Some(poll_games())).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert_eq!(test.game_runner_cmds.len(), 2);
+    assert!(matches!(
+        &test.game_runner_cmds[0],
+        GameRunner::SwitchTo(command) if command.name == "Game 2"
+    ));
+    assert!(matches!(test.game_runner_cmds[1], GameRunner::Stop));
+    assert_eq!(
+        test.announcements,
+        vec!["Scheduled: Game 2 in anarchy mode until 00:00".to_owned()]
+    );
+
+    let anarchy_mode: String = database::get_kv(&test.db_conn, "anarchy_mode")
+        .unwrap()
+        .unwrap();
+    assert_eq!(&anarchy_mode, AnarchyType::Anarchy.to_str());
+    let stop_pending: String = database::get_kv(&test.db_conn, "schedule_stop_pending")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stop_pending, "false");
+}
+
+#[tokio::test]
+async fn running_scheduled_slots_are_not_restarted() {
+    let (mut test, tx) = TestSetup::new();
+    let schedule = vec![all_day_slot()];
+    let run = crate::schedule::active_at(&schedule, chrono::Local::now().naive_local()).unwrap();
+    database::set_kv(&test.db_conn, "schedule_slot", run.key()).unwrap();
+    database::set_kv(&test.db_conn, "schedule_stop_pending", "true").unwrap();
+    test.schedule = Some(schedule);
+
+    let join_handle = tokio::task::spawn(async move {
+        let send = |command: Command| {
+            let mut tx = tx.clone();
+            let msg = Message {
+                command,
+                sender_id: "user_id".to_owned(),
+                sender_name: "user_name".to_owned(),
+                privilege: Privilege::Moderator,
+            };
+            async move { send_message(&mut tx, msg).await }
+        };
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(
+            send(Command::OverrideScheduledSlot).await,
+            Some(
+                "Overriding Game 2 in anarchy mode, nothing will be stopped when it ends at 00:00"
+                    .to_owned()
+            )
+        );
+        drop(tx);
+    });
+
+    test.run_with_games(Some(poll_games())).await.unwrap();
+    join_handle.await.unwrap();
+
+    assert!(test.game_runner_cmds.is_empty());
+    assert!(test.announcements.is_empty());
+    let stop_pending: String = database::get_kv(&test.db_conn, "schedule_stop_pending")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stop_pending, "false");
+}
+
+#[tokio::test]
+async fn schedule_commands_without_a_schedule() {
+    let (mut test, mut tx) = TestSetup::new();
+
+    let join_handle = tokio::task::spawn(async move {
+        let mut msg = Message {
+            command: Command::PrintSchedule,
+            sender_id: "user_id".to_owned(),
+            sender_name: "user_name".to_owned(),
+            privilege: Privilege::Moderator,
+        };
+        assert_eq!(
+            send_message(&mut tx, msg.clone()).await,
+            Some("No schedule is configured".to_owned())
+        );
+
+        msg.command = Command::SkipScheduledSlot;
+        assert_eq!(
+            send_message(&mut tx, msg).await,
+            Some("No scheduled slot is running".to_owned())
+        );
+    });
+
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn meter_votes_switch_mode() {
+    let (mut test, mut tx) = TestSetup::new();
+
+    database::set_kv(&test.db_conn, "anarchy_mode", "voting").unwrap();
+    database::set_kv(&test.db_conn, "meter_value", "65").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        for user in ["u1", "u2"] {
+            send_message(
+                &mut tx,
+                Message {
+                    command: Command::MeterVote(crate::meter::MeterVote::Anarchy),
+                    sender_id: user.to_owned(),
+                    sender_name: user.to_owned(),
+                    privilege: Privilege::Standard,
+                },
+            )
+            .await;
+        }
+
+        let response = send_message(
+            &mut tx,
+            Message {
+                command: Command::PrintAnarchyMode,
+                sender_id: "u1".to_owned(),
+                sender_name: "u1".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+        assert_eq!(
+            response,
+            Some("Current mode is anarchy, anarchy meter is at 75%".to_owned())
+        );
+    });
+
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+
+    let anarchy_mode: String = database::get_kv(&test.db_conn, "anarchy_mode")
+        .unwrap()
+        .unwrap();
+    assert_eq!(&anarchy_mode, AnarchyType::Anarchy.to_str());
+
+    let meter_value: String = database::get_kv(&test.db_conn, "meter_value")
+        .unwrap()
+        .unwrap();
+    assert_eq!(meter_value, "75");
+    assert_eq!(
+        test.announcements,
+        vec!["Chat switched the mode to anarchy, anarchy meter is at 75%".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn locked_meter_ignores_votes() {
+    let (mut test, mut tx) = TestSetup::new();
+    let mod_id = "mod_id".to_owned();
+    let mod_name = "mod_name".to_owned();
+
+    database::set_kv(&test.db_conn, "anarchy_mode", "anarchy").unwrap();
+    database::set_kv(&test.db_conn, "meter_value", "30").unwrap();
+
+    let join_handle = tokio::task::spawn(async move {
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::LockMeter(true),
+                sender_id: "u1".to_owned(),
+                sender_name: "u1".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+
+        send_message(
+            &mut tx,
+            Message {
+                command: Command::LockMeter(true),
+                sender_id: mod_id.clone(),
+                sender_name: mod_name.clone(),
+                privilege: Privilege::Moderator,
+            },
+        )
+        .await;
+
+        let response = send_message(
+            &mut tx,
+            Message {
+                command: Command::MeterVote(crate::meter::MeterVote::Democracy),
+                sender_id: "u1".to_owned(),
+                sender_name: "u1".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+        assert_eq!(response, Some("The anarchy meter is locked".to_owned()));
+
+        let response = send_message(
+            &mut tx,
+            Message {
+                command: Command::PrintAnarchyMode,
+                sender_id: "u1".to_owned(),
+                sender_name: "u1".to_owned(),
+                privilege: Privilege::Standard,
+            },
+        )
+        .await;
+        assert_eq!(
+            response,
+            Some("Current mode is anarchy, anarchy meter is at 30% (locked)".to_owned())
+        );
+    });
+
+    test.run().await.unwrap();
+    join_handle.await.unwrap();
+
+    let meter_locked: String = database::get_kv(&test.db_conn, "meter_locked")
+        .unwrap()
+        .unwrap();
+    assert_eq!(meter_locked, "true");
+    assert!(test.announcements.is_empty());
+}
@@ -1,5 +1,8 @@
 use serde::Deserialize;
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 use tokio::sync::{
     mpsc::{Sender, UnboundedReceiver, UnboundedSender},
     oneshot,
@@ -117,12 +120,13 @@ pub fn user_privilege(msg: &PrivmsgMessage, channel: &str) -> Privilege {
 async fn process_message<R>(
     tx: &mut Sender<command::WithReply<Message, R>>,
     channel: &str,
+    alias_words: &HashSet<String>,
     msg: &PrivmsgMessage,
 ) -> Option<oneshot::Receiver<R>> {
     trace!("Received: {:?}", msg);
     let privilege = user_privilege(msg, channel);
 
-    if let Some(command) = command::parse_command(&msg.message_text) {
+    if let Some(command) = command::parse_command_with_aliases(&msg.message_text, alias_words) {
         let command = Message {
             command,
             sender_name: msg.sender.login.clone(),
@@ -143,13 +147,29 @@ pub async fn run_twitch_irc<T: Transport, L: LoginCredentials>(
     client: TwitchIRCClient<T, L>,
     mut stream: UnboundedReceiver<ServerMessage>,
     channel: String,
+    alias_words: HashSet<String>,
     mut tx: Sender<command::WithReply<Message, Option<String>>>,
     mut sfx_runner: Option<UnboundedSender<SfxRequest>>,
+    mut announce_rx: UnboundedReceiver<String>,
 ) {
-    while let Some(msg) = stream.recv().await {
+    loop {
+        let msg = tokio::select! {
+            msg = stream.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            Some(announcement) = announce_rx.recv() => {
+                info!("Announcement: {}", announcement);
+                if let Err(err) = client.say(channel.clone(), announcement).await {
+                    error!("Error sending twitch announcement: {:?}", err);
+                }
+                continue;
+            }
+        };
+
         match msg {
             ServerMessage::Privmsg(msg) => {
-                let reply_rx = process_message(&mut tx, &channel, &msg).await;
+                let reply_rx = process_message(&mut tx, &channel, &alias_words, &msg).await;
                 let reply_rx = if let Some(reply_rx) = reply_rx {
                     reply_rx
                 } else {
@@ -192,13 +212,16 @@ pub async fn run_twitch_irc<T: Transport, L: LoginCredentials>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_twitch_irc_login(
     client: String,
     secret: String,
     token_path: &Path,
     channel: String,
+    alias_words: HashSet<String>,
     tx: Sender<command::WithReply<Message, Option<String>>>,
     sfx_runner: Option<UnboundedSender<SfxRequest>>,
+    announce_rx: UnboundedReceiver<String>,
 ) -> (tokio::task::JoinHandle<()>, tokio::task::JoinHandle<()>) {
     let store = CredStore {
         path: token_path.to_owned(),
@@ -214,7 +237,16 @@ pub fn run_twitch_irc_login(
         let channel = channel.clone();
         tokio::spawn(async move {
             info!("Starting twitch IRC on channel {}", channel);
-            run_twitch_irc(client, message_stream, channel, tx, sfx_runner).await;
+            run_twitch_irc(
+                client,
+                message_stream,
+                channel,
+                alias_words,
+                tx,
+                sfx_runner,
+                announce_rx,
+            )
+            .await;
         })
     };
 
@@ -224,8 +256,10 @@ pub fn run_twitch_irc_login(
 
 pub fn run_twitch_irc_anonymous(
     channel: String,
+    alias_words: HashSet<String>,
     tx: Sender<command::WithReply<Message, Option<String>>>,
     sfx_runner: Option<UnboundedSender<SfxRequest>>,
+    announce_rx: UnboundedReceiver<String>,
 ) -> (tokio::task::JoinHandle<()>, tokio::task::JoinHandle<()>) {
     let config = ClientConfig::default();
     let (message_stream, client) =
@@ -236,7 +270,16 @@ pub fn run_twitch_irc_anonymous(
         let channel = channel.clone();
         tokio::spawn(async move {
             info!("Starting twitch IRC on channel {}", channel);
-            run_twitch_irc(client, message_stream, channel, tx, sfx_runner).await;
+            run_twitch_irc(
+                client,
+                message_stream,
+                channel,
+                alias_words,
+                tx,
+                sfx_runner,
+                announce_rx,
+            )
+            .await;
         })
     };
 
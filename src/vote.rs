@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    First,
+    Random,
+    Skip,
+}
+
+impl TieBreak {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::First => "first",
+            Self::Random => "random",
+            Self::Skip => "skip",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "first" => Some(Self::First),
+            "random" => Some(Self::Random),
+            "skip" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// Picks a pseudo-random index in `0..len`, good enough for breaking ties
+pub fn random_index(len: usize) -> usize {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(nanos);
+    (hasher.finish() % len.max(1) as u64) as usize
+}
+
+#[derive(Debug)]
+pub struct Poll<T> {
+    /// Choices in the order they first received a vote
+    choices: Vec<(T, u64)>,
+    voters: HashMap<String, usize>,
+    closes_at: tokio::time::Instant,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PollResult<T> {
+    Winner(T),
+    Tied,
+    NotEnoughVotes(u64),
+}
+
+impl<T: PartialEq + Clone> Poll<T> {
+    pub fn new(window: std::time::Duration) -> Self {
+        Poll {
+            choices: Vec::new(),
+            voters: HashMap::new(),
+            closes_at: tokio::time::Instant::now() + window,
+        }
+    }
+
+    /// Records a vote, replacing any earlier vote by the same voter
+    pub fn vote(&mut self, voter: &str, choice: T) {
+        if let Some(previous) = self.voters.remove(voter) {
+            self.choices[previous].1 -= 1;
+        }
+
+        let idx = match self.choices.iter().position(|(c, _)| *c == choice) {
+            Some(idx) => idx,
+            None => {
+                self.choices.push((choice, 0));
+                self.choices.len() - 1
+            }
+        };
+
+        self.choices[idx].1 += 1;
+        self.voters.insert(voter.to_owned(), idx);
+    }
+
+    /// Time left until the poll closes
+    pub fn remaining(&self) -> std::time::Duration {
+        self.closes_at
+            .saturating_duration_since(tokio::time::Instant::now())
+    }
+
+    pub fn total_votes(&self) -> u64 {
+        self.choices.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Choices with at least one vote, most votes first
+    pub fn tally(&self) -> Vec<(&T, u64)> {
+        let mut tally: Vec<(&T, u64)> = self
+            .choices
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(choice, count)| (choice, *count))
+            .collect();
+
+        // Stable sort keeps the earliest choice first among equal counts
+        tally.sort_by(|(_, a), (_, b)| b.cmp(a));
+        tally
+    }
+
+    pub fn result(&self, tie_break: TieBreak, min_votes: u64) -> PollResult<T> {
+        let total = self.total_votes();
+        if total == 0 || total < min_votes {
+            return PollResult::NotEnoughVotes(total);
+        }
+
+        let tally = self.tally();
+        let top = tally[0].1;
+        let leaders: Vec<&T> = tally
+            .iter()
+            .filter(|(_, count)| *count == top)
+            .map(|(choice, _)| *choice)
+            .collect();
+
+        if leaders.len() == 1 {
+            return PollResult::Winner(leaders[0].clone());
+        }
+
+        match tie_break {
+            TieBreak::First => PollResult::Winner(leaders[0].clone()),
+            TieBreak::Random => PollResult::Winner(leaders[random_index(leaders.len())].clone()),
+            TieBreak::Skip => PollResult::Tied,
+        }
+    }
+}
+
+pub async fn wait_for_close<T>(poll: Option<&Poll<T>>) {
+    if let Some(poll) = poll {
+        tokio::time::sleep_until(poll.closes_at).await;
+    } else {
+        // Never resolve so that we don't spin excessively
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod poll_test {
+    use super::{Poll, PollResult, TieBreak};
+
+    fn poll() -> Poll<&'static str> {
+        Poll::new(std::time::Duration::from_secs(5))
+    }
+
+    #[test]
+    fn most_votes_wins() {
+        let mut poll = poll();
+        poll.vote("u1", "a");
+        poll.vote("u2", "b");
+        poll.vote("u3", "b");
+
+        assert_eq!(poll.total_votes(), 3);
+        assert_eq!(poll.tally(), vec![(&"b", 2), (&"a", 1)]);
+        assert_eq!(poll.result(TieBreak::Skip, 1), PollResult::Winner("b"));
+    }
+
+    #[test]
+    fn voters_can_change_their_vote() {
+        let mut poll = poll();
+        poll.vote("u1", "a");
+        poll.vote("u2", "a");
+        poll.vote("u1", "b");
+        poll.vote("u1", "c");
+
+        assert_eq!(poll.total_votes(), 2);
+        assert_eq!(poll.tally(), vec![(&"a", 1), (&"c", 1)]);
+    }
+
+    #[test]
+    fn ties_are_broken_by_policy() {
+        let mut poll = poll();
+        poll.vote("u1", "a");
+        poll.vote("u2", "b");
+
+        assert_eq!(poll.result(TieBreak::First, 1), PollResult::Winner("a"));
+        assert_eq!(poll.result(TieBreak::Skip, 1), PollResult::Tied);
+        assert!(matches!(
+            poll.result(TieBreak::Random, 1),
+            PollResult::Winner("a" | "b")
+        ));
+    }
+
+    #[test]
+    fn minimum_votes_are_required() {
+        let mut poll = poll();
+        poll.vote("u1", "a");
+        poll.vote("u2", "a");
+
+        assert_eq!(
+            poll.result(TieBreak::First, 3),
+            PollResult::NotEnoughVotes(2)
+        );
+        assert_eq!(poll.result(TieBreak::First, 2), PollResult::Winner("a"));
+    }
+}